@@ -0,0 +1,64 @@
+// Las transiciones se etiquetan con clases de caracteres: "a" para un solo
+// caracter o "a-z" para un rango. Las clases de un DFA son disjuntas, así que
+// cada caracter de la entrada sigue a lo más una transición.
+
+pub fn class_key(start: char, end: char) -> String {
+    if start == end {
+        start.to_string()
+    } else {
+        format!("{}-{}", start, end)
+    }
+}
+
+pub fn key_range(key: &str) -> (char, char) {
+    let parts: Vec<char> = key.chars().collect();
+    match parts.len() {
+        1 => (parts[0], parts[0]),
+        3 if parts[1] == '-' => (parts[0], parts[2]),
+        _ => panic!("Invalid transition symbol {:?}", key),
+    }
+}
+
+pub fn key_matches(key: &str, c: char) -> bool {
+    let (start, end) = key_range(key);
+    start <= c && c <= end
+}
+
+/// Parte los rangos en clases disjuntas: cada clase queda dentro o fuera de
+/// cada rango original. Solo devuelve las clases cubiertas por algún rango.
+pub fn split_classes(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut cuts: Vec<u32> = Vec::new();
+    for &(start, end) in ranges {
+        cuts.push(start as u32);
+        cuts.push(end as u32 + 1);
+    }
+    cuts.sort();
+    cuts.dedup();
+
+    let mut classes = Vec::new();
+    for pair in cuts.windows(2) {
+        let (start, end) = (pair[0], pair[1] - 1);
+        let covered = ranges
+            .iter()
+            .any(|&(s, e)| s as u32 <= start && end <= e as u32);
+        if !covered {
+            continue;
+        }
+        // Los surrogates no son chars válidos, la clase se recorta alrededor
+        let (Some(start), Some(end)) = (valid_char(start, true), valid_char(end, false)) else {
+            continue;
+        };
+        if start <= end {
+            classes.push((start, end));
+        }
+    }
+    classes
+}
+
+fn valid_char(code: u32, upwards: bool) -> Option<char> {
+    if (0xD800..=0xDFFF).contains(&code) {
+        let code = if upwards { 0xE000 } else { 0xD7FF };
+        return char::from_u32(code);
+    }
+    char::from_u32(code)
+}
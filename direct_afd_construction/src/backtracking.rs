@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::dfa::Dfa;
+
+// Con maximal munch el scanner sigue leyendo después de un estado de aceptación
+// por estados que no aceptan; si la entrada se corta ahí tiene que retroceder
+// hasta la última posición que aceptó. La distancia de retroceso es el camino
+// más largo por estados que no aceptan. Desde el estado inicial no hay a dónde
+// retroceder (es un error léxico), así que solo cuenta si acepta.
+//
+// Con contexto posterior r/s el scanner acepta al final de s y después corta el
+// lexema en la última marca de r: devuelve todo lo leído desde esa marca. Ese
+// retroceso es el camino más largo desde un estado marcado hasta uno que acepta
+// el token sin pasar por otra marca, más lo que se haya leído de más después.
+
+/// Peor retroceso del scanner. `distance` es None si no tiene cota: hay un
+/// ciclo de estados que no aceptan, y `states` son los de ese ciclo. Si no, son
+/// los estados del peor camino en orden.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backtracking {
+    pub distance: Option<usize>,
+    pub states: Vec<char>,
+}
+
+fn non_accepting_successors(dfa: &Dfa, state: char) -> Vec<char> {
+    let mut next: Vec<char> = dfa.transitions[&state]
+        .values()
+        .filter(|dest| !dfa.accept.contains_key(dest))
+        .cloned()
+        .collect();
+    next.sort();
+    next.dedup();
+    next
+}
+
+// Largo del camino más largo que empieza en `state` sin pasar por estados que
+// aceptan, junto al siguiente estado de ese camino. Err con el ciclo si lo hay.
+fn visit(
+    dfa: &Dfa,
+    state: char,
+    path: &mut Vec<char>,
+    longest: &mut HashMap<char, (usize, Option<char>)>,
+) -> Result<usize, Vec<char>> {
+    if let Some(&(len, _)) = longest.get(&state) {
+        return Ok(len);
+    }
+    if let Some(pos) = path.iter().position(|&s| s == state) {
+        let mut cycle = path[pos..].to_vec();
+        cycle.sort();
+        return Err(cycle);
+    }
+    path.push(state);
+    let mut best = (1, None);
+    for next in non_accepting_successors(dfa, state) {
+        let len = visit(dfa, next, path, longest)? + 1;
+        if len > best.0 {
+            best = (len, Some(next));
+        }
+    }
+    path.pop();
+    longest.insert(state, best);
+    Ok(best.0)
+}
+
+// Lo que se lee de más después de aceptar en `state`, con el primer estado
+fn overrun(
+    dfa: &Dfa,
+    state: char,
+    longest: &mut HashMap<char, (usize, Option<char>)>,
+) -> Result<(usize, Option<char>), Vec<char>> {
+    let mut worst = (0, None);
+    for next in non_accepting_successors(dfa, state) {
+        let len = visit(dfa, next, &mut Vec::new(), longest)?;
+        if len > worst.0 {
+            worst = (len, Some(next));
+        }
+    }
+    Ok(worst)
+}
+
+fn marked(dfa: &Dfa, state: char, token: &str) -> bool {
+    dfa.trail.get(&state).is_some_and(|tokens| tokens.contains(token))
+}
+
+// Retroceso del corte de r/s para un token. Solo se recorren los estados de
+// `useful`, que llegan a aceptar el token sin pasar por otra marca; un ciclo
+// entre ellos no tiene cota.
+struct TrailSearch<'a> {
+    dfa: &'a Dfa,
+    token: &'a str,
+    useful: HashSet<char>,
+    /// Estado -> (largo, siguiente estado, el siguiente ya es lo leído de más)
+    longest: HashMap<char, (usize, Option<char>, bool)>,
+}
+
+impl TrailSearch<'_> {
+    // Desde un estado sin marca ya leído: lo que falta hasta aceptar el token
+    // más lo que se lee de más después
+    fn visit(
+        &mut self,
+        state: char,
+        path: &mut Vec<char>,
+        overrun_longest: &mut HashMap<char, (usize, Option<char>)>,
+    ) -> Result<usize, Vec<char>> {
+        if let Some(&(len, _, _)) = self.longest.get(&state) {
+            return Ok(len);
+        }
+        if let Some(pos) = path.iter().position(|&s| s == state) {
+            let mut cycle = path[pos..].to_vec();
+            cycle.sort();
+            return Err(cycle);
+        }
+        path.push(state);
+        // Si acepta el token el corte puede venir de aquí
+        let mut best: Option<(usize, Option<char>, bool)> = None;
+        if self.dfa.accept.get(&state).is_some_and(|t| t == self.token) {
+            let (len, next) = overrun(self.dfa, state, overrun_longest)?;
+            best = Some((len + 1, next, true));
+        }
+        for next in self.successors(state) {
+            let len = self.visit(next, path, overrun_longest)? + 1;
+            if best.is_none_or(|(b, _, _)| len > b) {
+                best = Some((len, Some(next), false));
+            }
+        }
+        path.pop();
+        let best = best.expect("useful states reach an accepting state");
+        self.longest.insert(state, best);
+        Ok(best.0)
+    }
+
+    fn successors(&self, state: char) -> Vec<char> {
+        let mut next: Vec<char> = self.dfa.transitions[&state]
+            .values()
+            .filter(|dest| self.useful.contains(dest))
+            .cloned()
+            .collect();
+        next.sort();
+        next.dedup();
+        next
+    }
+
+    // Estados del camino desde `state`, pasando a lo leído de más al aceptar
+    fn path(&self, state: char, overrun_longest: &HashMap<char, (usize, Option<char>)>) -> Vec<char> {
+        let mut states = vec![state];
+        let (_, mut current, mut in_overrun) = self.longest[&state];
+        while let Some(state) = current {
+            states.push(state);
+            if in_overrun {
+                current = overrun_longest[&state].1;
+            } else {
+                (_, current, in_overrun) = self.longest[&state];
+            }
+        }
+        states
+    }
+}
+
+// Estados sin marca de `token` desde los que se llega a aceptarlo sin pasar
+// por una marca
+fn useful_states(dfa: &Dfa, token: &str) -> HashSet<char> {
+    let mut useful: HashSet<char> = dfa
+        .accept
+        .iter()
+        .filter(|(state, t)| *t == token && !marked(dfa, **state, token))
+        .map(|(state, _)| *state)
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (state, row) in &dfa.transitions {
+            if !useful.contains(state)
+                && !marked(dfa, *state, token)
+                && row.values().any(|dest| useful.contains(dest))
+            {
+                useful.insert(*state);
+                changed = true;
+            }
+        }
+    }
+    useful
+}
+
+/// Peor cantidad de caracteres que el scanner puede tener que devolver, sobre
+/// el DFA ya minimizado.
+pub fn backtracking(dfa: &Dfa) -> Backtracking {
+    let dfa = dfa.prune();
+    match worst_path(&dfa) {
+        Ok((distance, states)) => Backtracking {
+            distance: Some(distance),
+            states,
+        },
+        Err(cycle) => Backtracking {
+            distance: None,
+            states: cycle,
+        },
+    }
+}
+
+// Peor retroceso con los estados de su camino; Err con el ciclo si no tiene cota
+fn worst_path(dfa: &Dfa) -> Result<(usize, Vec<char>), Vec<char>> {
+    let mut origins: Vec<char> = dfa.accept.keys().cloned().collect();
+    origins.sort();
+
+    let mut longest: HashMap<char, (usize, Option<char>)> = HashMap::new();
+    let mut worst: (usize, Option<char>) = (0, None);
+    for origin in origins {
+        let (len, next) = overrun(dfa, origin, &mut longest)?;
+        if len > worst.0 {
+            worst = (len, next);
+        }
+    }
+    let mut states = Vec::new();
+    let mut current = worst.1;
+    while let Some(state) = current {
+        states.push(state);
+        current = longest[&state].1;
+    }
+
+    let mut tokens: Vec<&String> = dfa.trail.values().flatten().collect();
+    tokens.sort();
+    tokens.dedup();
+    for token in tokens {
+        let mut search = TrailSearch {
+            dfa,
+            token,
+            useful: useful_states(dfa, token),
+            longest: HashMap::new(),
+        };
+        let mut marks: Vec<char> = dfa
+            .transitions
+            .keys()
+            .filter(|&&state| marked(dfa, state, token))
+            .cloned()
+            .collect();
+        marks.sort();
+        for mark in marks {
+            for next in search.successors(mark) {
+                let len = search.visit(next, &mut Vec::new(), &mut longest)?;
+                if len > states.len() {
+                    states = search.path(next, &longest);
+                }
+            }
+        }
+    }
+    Ok((states.len(), states))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+
+    fn lexer(rules: &[&str]) -> Dfa {
+        let mut builder = LexerBuilder::new();
+        for (index, pattern) in rules.iter().enumerate() {
+            builder.add_rule(index, parse_rule(pattern));
+        }
+        minimize_dfa(&builder.build())
+    }
+
+    #[test]
+    fn test_backtracking_distance() {
+        let free = backtracking(&lexer(&["[a-z]+", "[0-9]+"]));
+        assert_eq!(free.distance, Some(0));
+        assert!(free.states.is_empty());
+
+        // "1." no acepta: con "1.x" se devuelve el punto
+        let float = backtracking(&lexer(&["[0-9]+", "[0-9]+\\.[0-9]+"]));
+        assert_eq!(float.distance, Some(1));
+        assert_eq!(float.states.len(), 1);
+
+        // Después de "a" se pueden leer b sin cota antes de la c
+        let unbounded = backtracking(&lexer(&["a", "ab*c"]));
+        assert_eq!(unbounded.distance, None);
+        assert_eq!(unbounded.states.len(), 1);
+
+        // Antes de "whil" no hubo aceptación: no es retroceso sino error
+        let keywords = backtracking(&lexer(&["while", "return"]));
+        assert_eq!(keywords.distance, Some(0));
+        let prefix = backtracking(&lexer(&["if", "ifdef"]));
+        assert_eq!(prefix.distance, Some(2));
+        assert_eq!(prefix.states.len(), 2);
+
+        // Con r/s el scanner acepta "1.." y devuelve la parte s, el lexema es "1"
+        let trail = backtracking(&lexer(&["[0-9]+/\\.\\.", "[0-9]+", "\\.\\."]));
+        assert_eq!(trail.distance, Some(2));
+        assert_eq!(trail.states.len(), 2);
+        // Todos los estados aceptan pero s = b* no tiene cota
+        let unbounded_trail = backtracking(&lexer(&["a/b*", "c"]));
+        assert_eq!(unbounded_trail.distance, None);
+        assert_eq!(unbounded_trail.states.len(), 1);
+        // Lo que se lee de más después de s también se devuelve: con "1...y" se
+        // vuelve a "1.." y de ahí a "1"
+        let overrun = backtracking(&lexer(&["[0-9]+/\\.\\.", "[0-9]+", "[0-9]+\\.\\.\\.x"]));
+        assert_eq!(overrun.distance, Some(3));
+    }
+}
@@ -1,28 +1,251 @@
-fn act(toks: Vec<String>){
-    let tk_list:Vec<String>= Vec::new();
-    for t in toks{if t=="5" { tk_list.push(INT); }
-if t=="3" { tk_list.push(FLOAT); }
-if t=="4" { tk_list.push(SIGNED); }
-if t=="6" { tk_list.push(WHILE); }
-if t=="11" { tk_list.push(GT); }
-if t=="8" { tk_list.push(L_BRACE); }
-if t=="12" { tk_list.push(GTE); }
-if t=="14" { tk_list.push(LTE); }
-if t=="10" { tk_list.push(ASIGN); }
-if t=="15" {  }
-if t=="16" { tk_list.push(RETURN); }
-if t=="7" { tk_list.push(ID); }
-if t=="13" { tk_list.push(LT); }
-if t=="9" { tk_list.push(R_BRACE); }
-   }
-     println!("{:?}",tk_list); 
+// Generado por direct_afd_construction desde ./test.yal, no editar
+use std::fmt;
+use std::io::Read;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Int,
+    Float,
+    Signed,
+    While,
+    Id,
+    LBrace,
+    RBrace,
+    Asign,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Range,
+    Return,
+    Directive,
+    Eof,
 }
+
+impl TokenKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::Int => "INT",
+            TokenKind::Float => "FLOAT",
+            TokenKind::Signed => "SIGNED",
+            TokenKind::While => "WHILE",
+            TokenKind::Id => "ID",
+            TokenKind::LBrace => "L_BRACE",
+            TokenKind::RBrace => "R_BRACE",
+            TokenKind::Asign => "ASIGN",
+            TokenKind::Gt => "GT",
+            TokenKind::Gte => "GTE",
+            TokenKind::Lt => "LT",
+            TokenKind::Lte => "LTE",
+            TokenKind::Range => "RANGE",
+            TokenKind::Return => "RETURN",
+            TokenKind::Directive => "DIRECTIVE",
+            TokenKind::Eof => "EOF",
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Token de cada regla en orden de prioridad, None si la regla no emite
+const RULES: [Option<TokenKind>; 18] = [
+    Some(TokenKind::Int),
+    Some(TokenKind::Float),
+    Some(TokenKind::Signed),
+    Some(TokenKind::Int),
+    Some(TokenKind::While),
+    Some(TokenKind::Id),
+    Some(TokenKind::LBrace),
+    Some(TokenKind::RBrace),
+    Some(TokenKind::Asign),
+    Some(TokenKind::Gt),
+    Some(TokenKind::Gte),
+    Some(TokenKind::Lt),
+    Some(TokenKind::Lte),
+    Some(TokenKind::Range),
+    None,
+    Some(TokenKind::Return),
+    None,
+    Some(TokenKind::Directive),
+];
+const EOF_TOKEN: Option<TokenKind> = Some(TokenKind::Eof);
+const START: usize = 0;
+const BOL_START: usize = 1;
+
+// Regla que gana en cada estado
+const ACCEPT: [Option<usize>; 31] = [
+    None,
+    None,
+    Some(14),
+    None,
+    None,
+    None,
+    Some(3),
+    Some(11),
+    Some(8),
+    Some(9),
+    Some(5),
+    Some(5),
+    Some(5),
+    Some(6),
+    Some(7),
+    None,
+    Some(2),
+    Some(13),
+    None,
+    Some(1),
+    Some(12),
+    Some(10),
+    Some(5),
+    Some(17),
+    Some(1),
+    None,
+    Some(0),
+    Some(5),
+    Some(16),
+    Some(5),
+    Some(4),
+];
+// Reglas r/s cuya parte r termina en cada estado
+const TRAIL: [&[usize]; 31] = [
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[0],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+    &[],
+];
+
+// Por estado: (desde, hasta, destino)
+const TRANSITIONS: [&[(char, char, usize)]; 31] = [
+    &[('\t', '\t', 2), ('\n', '\n', 2), (' ', ' ', 2), ('-', '-', 3), ('.', '.', 4), ('/', '/', 5), ('0', '9', 6), ('<', '<', 7), ('=', '=', 8), ('>', '>', 9), ('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 11), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 12), ('x', 'z', 10), ('{', '{', 13), ('}', '}', 14)],
+    &[('\t', '\t', 2), ('\n', '\n', 2), (' ', ' ', 2), ('#', '#', 15), ('-', '-', 3), ('.', '.', 4), ('/', '/', 5), ('0', '9', 6), ('<', '<', 7), ('=', '=', 8), ('>', '>', 9), ('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 11), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 12), ('x', 'z', 10), ('{', '{', 13), ('}', '}', 14)],
+    &[('\t', '\t', 2), ('\n', '\n', 2), (' ', ' ', 2), ('s', 's', 2)],
+    &[('0', '9', 16)],
+    &[('.', '.', 17)],
+    &[('*', '*', 18)],
+    &[('.', '.', 19), ('0', '9', 6)],
+    &[('=', '=', 20)],
+    &[],
+    &[('=', '=', 21)],
+    &[('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 10), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+    &[('\t', '\t', 2), ('\n', '\n', 2), (' ', ' ', 2), ('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 11), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+    &[('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 22), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 10), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+    &[],
+    &[],
+    &[('a', 'd', 23), ('e', 'e', 23), ('f', 'g', 23), ('h', 'h', 23), ('i', 'i', 23), ('j', 'k', 23), ('l', 'l', 23), ('m', 'm', 23), ('n', 'n', 23), ('o', 'q', 23), ('r', 'r', 23), ('s', 's', 23), ('t', 't', 23), ('u', 'u', 23), ('v', 'v', 23), ('w', 'w', 23), ('x', 'z', 23)],
+    &[('.', '.', 24), ('0', '9', 16)],
+    &[],
+    &[('\0', '\u{8}', 18), ('\t', '\t', 18), ('\n', '\n', 18), ('\u{b}', '\u{1f}', 18), (' ', ' ', 18), ('!', '"', 18), ('#', '#', 18), ('$', ')', 18), ('*', '*', 25), ('+', ',', 18), ('-', '-', 18), ('.', '.', 18), ('/', '/', 18), ('0', '9', 18), (':', ';', 18), ('<', '<', 18), ('=', '=', 18), ('>', '>', 18), ('?', '`', 18), ('a', 'd', 18), ('e', 'e', 18), ('f', 'g', 18), ('h', 'h', 18), ('i', 'i', 18), ('j', 'k', 18), ('l', 'l', 18), ('m', 'm', 18), ('n', 'n', 18), ('o', 'q', 18), ('r', 'r', 18), ('s', 's', 18), ('t', 't', 18), ('u', 'u', 18), ('v', 'v', 18), ('w', 'w', 18), ('x', 'z', 18), ('{', '{', 18), ('|', '|', 18), ('}', '}', 18), ('~', '\u{10ffff}', 18)],
+    &[('.', '.', 26), ('0', '9', 24)],
+    &[],
+    &[],
+    &[('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 27), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 10), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+    &[('a', 'd', 23), ('e', 'e', 23), ('f', 'g', 23), ('h', 'h', 23), ('i', 'i', 23), ('j', 'k', 23), ('l', 'l', 23), ('m', 'm', 23), ('n', 'n', 23), ('o', 'q', 23), ('r', 'r', 23), ('s', 's', 23), ('t', 't', 23), ('u', 'u', 23), ('v', 'v', 23), ('w', 'w', 23), ('x', 'z', 23)],
+    &[('0', '9', 24)],
+    &[('\0', '\u{8}', 18), ('\t', '\t', 18), ('\n', '\n', 18), ('\u{b}', '\u{1f}', 18), (' ', ' ', 18), ('!', '"', 18), ('#', '#', 18), ('$', ')', 18), ('*', '*', 25), ('+', ',', 18), ('-', '-', 18), ('.', '.', 18), ('/', '/', 28), ('0', '9', 18), (':', ';', 18), ('<', '<', 18), ('=', '=', 18), ('>', '>', 18), ('?', '`', 18), ('a', 'd', 18), ('e', 'e', 18), ('f', 'g', 18), ('h', 'h', 18), ('i', 'i', 18), ('j', 'k', 18), ('l', 'l', 18), ('m', 'm', 18), ('n', 'n', 18), ('o', 'q', 18), ('r', 'r', 18), ('s', 's', 18), ('t', 't', 18), ('u', 'u', 18), ('v', 'v', 18), ('w', 'w', 18), ('x', 'z', 18), ('{', '{', 18), ('|', '|', 18), ('}', '}', 18), ('~', '\u{10ffff}', 18)],
+    &[],
+    &[('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 29), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 10), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+    &[],
+    &[('a', 'd', 10), ('e', 'e', 30), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 10), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+    &[('a', 'd', 10), ('e', 'e', 10), ('f', 'g', 10), ('h', 'h', 10), ('i', 'i', 10), ('j', 'k', 10), ('l', 'l', 10), ('m', 'm', 10), ('n', 'n', 10), ('o', 'q', 10), ('r', 'r', 10), ('s', 's', 10), ('t', 't', 10), ('u', 'u', 10), ('v', 'v', 10), ('w', 'w', 10), ('x', 'z', 10)],
+];
+
+fn step(state: usize, c: char) -> Option<usize> {
+    TRANSITIONS[state]
+        .iter()
+        .find(|&&(lo, hi, _)| lo <= c && c <= hi)
+        .map(|&(_, _, dest)| dest)
+}
+
+/// Tokens de la entrada con su lexema. Err con la posición (en caracteres)
+/// donde no empieza ningún token.
+pub fn scan(input: &str) -> Result<Vec<(TokenKind, String)>, usize> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let line_start = pos == 0 || chars[pos - 1] == '\n';
+        let mut state = if line_start { BOL_START } else { START };
+        // Última posición donde terminó la parte r de cada regla r/s
+        let mut marks: Vec<Option<usize>> = vec![None; RULES.len()];
+        let mut last: Option<(usize, usize, Option<usize>)> = None;
+        let mut read = 0;
+        loop {
+            for &rule in TRAIL[state] {
+                marks[rule] = Some(read);
+            }
+            if let Some(rule) = ACCEPT[state] {
+                last = Some((read, rule, marks[rule]));
+            }
+            match chars.get(pos + read).and_then(|&c| step(state, c)) {
+                Some(next) => state = next,
+                None => break,
+            }
+            read += 1;
+        }
+        let (len, rule) = match last {
+            Some((_, rule, Some(cut))) if cut > 0 => (cut, rule),
+            Some((len, rule, _)) if len > 0 => (len, rule),
+            _ => return Err(pos),
+        };
+        if let Some(kind) = RULES[rule] {
+            tokens.push((kind, chars[pos..pos + len].iter().collect()));
+        }
+        pos += len;
+    }
+    if let Some(kind) = EOF_TOKEN {
+        tokens.push((kind, String::new()));
+    }
+    Ok(tokens)
+}
+
 fn main() {
-let reg = r"(((-?)[0-9]+.[0-9]*)({3}))|((-[0-9]+)({4}))|(([0-9]+)({5}))|((while)({6}))|(([a-z]+)({7}))|((\{)({8}))|((\})({9}))|((=)({10}))|((>)({11}))|((>=)({12}))|((<)({13}))|((<=)({14}))|((( |\n|\t|\s)+)({15}))|((return)({16}))";
-let (minimized_map, minimized_accept_states,minimized_start, token_list) = generate(reg);
-    let toks = simulate(input.to_string(), minimized_map, minimized_accept_states, minimized_start, token_list);
-    let mut reslut: Vec<String> = Vec::new();
-    for t in toks{
-        act(t);
+    let mut input = String::new();
+    match std::env::args().nth(1) {
+        Some(path) => input = std::fs::read_to_string(path).expect("Cannot read input file"),
+        None => {
+            std::io::stdin().read_to_string(&mut input).expect("Cannot read stdin");
+        }
     }
-}
\ No newline at end of file
+    match scan(&input) {
+        Ok(tokens) => {
+            for (kind, lexeme) in tokens {
+                println!("{}\t{:?}", kind, lexeme);
+            }
+        }
+        Err(pos) => {
+            eprintln!("Token no identificado en la posición {}", pos);
+            std::process::exit(1);
+        }
+    }
+}
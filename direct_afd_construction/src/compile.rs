@@ -1,20 +1,36 @@
+use crate::alphabet::key_range;
+use crate::compress::{compress, table_sizes, TableSizes};
+use crate::dfa::Dfa;
+use crate::grammar_tree::TreeNode;
 use crate::lex_reader::get_line_array;
+use crate::regex_parser;
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::fs::File;
 use std::io::Write;
 
 
-pub fn gen_reg()->(String, HashMap<u8,String>){
-    let lex = "./test.yal";
-    let mut map: HashMap<u8,String> = HashMap::new();
+// El árbol de cada regla con su línea, las acciones por línea y la línea de la regla eof
+pub type SpecRules = (Vec<(usize, TreeNode)>, HashMap<usize,String>, Option<usize>);
+
+// Devuelve el árbol de cada regla con su índice, las acciones por token y el token de la regla eof
+pub fn gen_rules(lex: &str)->SpecRules{
+    let mut map: HashMap<usize,String> = HashMap::new();
+    let mut eof_token: Option<usize> = None;
     let acts = get_line_array(lex);
-    let mut reg_array : Vec<String> = Vec::new();
+    let mut rules : Vec<(usize, TreeNode)> = Vec::new();
     for ac in acts{
-        reg_array.push(format!("(({})({{{}}}))",ac.0, ac.2));
+        // eof no es un patrón, el scanner lo emite al terminar la entrada
+        if ac.3{
+            if eof_token.is_none(){
+                eof_token = Some(ac.2);
+            }
+        } else{
+            rules.push((ac.2, regex_parser::parse_rule(&ac.0)));
+        }
         map.insert(ac.2, ac.1);
     }
-    let result = reg_array.join("|");
-    (String::from(result),map)
+    (rules, map, eof_token)
 }
 
 
@@ -23,4 +39,510 @@ pub fn creat_file(cont: String)->std::io::Result<()>{
     let byte_slice: &[u8] = cont.as_bytes();
     file.write_all(byte_slice)?; // Writes to the file
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Nombre del token de una acción: `tk_list.push("INT");` es INT y `{ INT }`
+/// también. Las acciones vacías (espacios, comentarios) no emiten token.
+pub fn token_name(action: &str) -> Option<String> {
+    let action = action.trim();
+    let name = match action.find("push(") {
+        Some(start) => action[start + 5..].split(')').next().unwrap_or_default(),
+        None => action.trim_end_matches(';'),
+    };
+    let name = name.trim().trim_matches('"');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// L_BRACE -> LBrace; si el nombre no sirve como identificador, Rule<línea>
+fn variant_name(name: &str, line: usize) -> String {
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().any(|c| c.is_ascii_alphabetic())
+        && !name.starts_with(|c: char| c.is_ascii_digit());
+    if !valid {
+        return format!("Rule{}", line);
+    }
+    let variant: String = name
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            first.to_string() + &chars.as_str().to_ascii_lowercase()
+        })
+        .collect();
+    if variant == "Self" {
+        "SelfToken".to_string()
+    } else {
+        variant
+    }
+}
+
+/// Variante de `TokenKind` por nombre de token, en orden de aparición en el
+/// spec. Las reglas con el mismo nombre comparten variante.
+pub fn token_kinds(
+    lines: &[usize],
+    actions: &HashMap<usize, String>,
+) -> (Vec<(String, String)>, HashMap<usize, String>) {
+    let mut kinds: Vec<(String, String)> = Vec::new();
+    let mut by_line: HashMap<usize, String> = HashMap::new();
+    for &line in lines {
+        let Some(name) = actions.get(&line).and_then(|a| token_name(a)) else {
+            continue;
+        };
+        let variant = match kinds.iter().find(|(_, n)| *n == name) {
+            Some((variant, _)) => variant.clone(),
+            None => {
+                let mut variant = variant_name(&name, line);
+                if kinds.iter().any(|(v, _)| *v == variant) {
+                    variant = format!("{}{}", variant, line);
+                }
+                kinds.push((variant.clone(), name));
+                variant
+            }
+        };
+        by_line.insert(line, variant);
+    }
+    (kinds, by_line)
+}
+
+/// Cómo se emiten las transiciones del scanner generado. Las dos formas
+/// comparten `scan`, así que dan los mismos tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emission {
+    /// Tabla de rangos por estado que recorre `step`
+    Tables,
+    /// Un `match` sobre el caracter por estado, como re2c
+    DirectCoded,
+    /// Clases de equivalencia y filas empacadas con transición por defecto
+    Compressed,
+}
+
+// Estados en orden de nombre; su posición es el número en el código generado
+fn state_index(dfa: &Dfa) -> (Vec<char>, HashMap<char, usize>) {
+    let mut states: Vec<char> = dfa.transitions.keys().cloned().collect();
+    states.push(dfa.start);
+    states.extend(dfa.bol_start);
+    states.sort();
+    states.dedup();
+    let index = states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    (states, index)
+}
+
+// Transiciones de cada estado como (desde, hasta, destino), en orden
+fn transition_rows(dfa: &Dfa, states: &[char], index: &HashMap<char, usize>) -> Vec<Vec<(char, char, usize)>> {
+    states
+        .iter()
+        .map(|state| {
+            let mut row: Vec<(char, char, usize)> = dfa
+                .transitions
+                .get(state)
+                .into_iter()
+                .flatten()
+                .map(|(key, dest)| {
+                    let (lo, hi) = key_range(key);
+                    (lo, hi, index[dest])
+                })
+                .collect();
+            row.sort();
+            row
+        })
+        .collect()
+}
+
+/// Tamaño de las tablas de transiciones del scanner, densas, por rangos y
+/// comprimidas.
+pub fn scanner_table_sizes(dfa: &Dfa) -> TableSizes {
+    let (states, index) = state_index(dfa);
+    table_sizes(&transition_rows(dfa, &states, &index))
+}
+
+/// Código de un scanner autocontenido: el enum `TokenKind`, las tablas del
+/// DFA y `scan`, que hace maximal munch con r/s y ^ como `simulate`.
+pub fn emit_scanner(
+    spec: &str,
+    dfa: &Dfa,
+    actions: &HashMap<usize, String>,
+    eof_token: Option<usize>,
+    emission: Emission,
+) -> String {
+    let mut rule_lines: Vec<usize> = dfa.token_list.iter().map(|t| t.parse().unwrap()).collect();
+    rule_lines.extend(eof_token);
+    let (kinds, by_line) = token_kinds(&rule_lines, actions);
+    let (states, index) = state_index(dfa);
+    let rule_index: HashMap<String, usize> = dfa
+        .token_list
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.clone(), i))
+        .collect();
+    let kind = |line: &usize| match by_line.get(line) {
+        Some(variant) => format!("Some(TokenKind::{})", variant),
+        None => "None".to_string(),
+    };
+
+    let mut out = String::new();
+    writeln!(out, "// Generado por direct_afd_construction desde {}, no editar", spec).unwrap();
+    out += "use std::fmt;\nuse std::io::Read;\n\n";
+    out += "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\npub enum TokenKind {\n";
+    for (variant, _) in &kinds {
+        writeln!(out, "    {},", variant).unwrap();
+    }
+    out += "}\n\nimpl TokenKind {\n    pub fn name(&self) -> &'static str {\n        match self {\n";
+    for (variant, name) in &kinds {
+        writeln!(out, "            TokenKind::{} => {:?},", variant, name).unwrap();
+    }
+    out += "        }\n    }\n}\n\n";
+    out += "impl fmt::Display for TokenKind {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        f.write_str(self.name())\n    }\n}\n\n";
+
+    out += "// Token de cada regla en orden de prioridad, None si la regla no emite\n";
+    writeln!(out, "const RULES: [Option<TokenKind>; {}] = [", dfa.token_list.len()).unwrap();
+    for token in &dfa.token_list {
+        writeln!(out, "    {},", kind(&token.parse().unwrap())).unwrap();
+    }
+    out += "];\n";
+    writeln!(out, "const EOF_TOKEN: Option<TokenKind> = {};", eof_token.map_or("None".to_string(), |l| kind(&l))).unwrap();
+    writeln!(out, "const START: usize = {};", index[&dfa.start]).unwrap();
+    writeln!(out, "const BOL_START: usize = {};", index[&dfa.bol_start.unwrap_or(dfa.start)]).unwrap();
+
+    out += "\n// Regla que gana en cada estado\n";
+    writeln!(out, "const ACCEPT: [Option<usize>; {}] = [", states.len()).unwrap();
+    for state in &states {
+        match dfa.accept.get(state) {
+            Some(token) => writeln!(out, "    Some({}),", rule_index[token]).unwrap(),
+            None => out += "    None,\n",
+        }
+    }
+    out += "];\n// Reglas r/s cuya parte r termina en cada estado\n";
+    writeln!(out, "const TRAIL: [&[usize]; {}] = [", states.len()).unwrap();
+    for state in &states {
+        let mut rules: Vec<usize> = dfa
+            .trail
+            .get(state)
+            .into_iter()
+            .flatten()
+            .map(|t| rule_index[t])
+            .collect();
+        rules.sort();
+        let rules: Vec<String> = rules.iter().map(|r| r.to_string()).collect();
+        writeln!(out, "    &[{}],", rules.join(", ")).unwrap();
+    }
+    out += "];\n\n";
+
+    let rows = transition_rows(dfa, &states, &index);
+    match emission {
+        Emission::Tables => emit_table_step(&mut out, &rows),
+        Emission::DirectCoded => emit_direct_step(&mut out, &rows),
+        Emission::Compressed => emit_compressed_step(&mut out, &rows),
+    }
+    out += SCANNER;
+    out
+}
+
+fn emit_table_step(out: &mut String, rows: &[Vec<(char, char, usize)>]) {
+    *out += "// Por estado: (desde, hasta, destino)\n";
+    writeln!(out, "const TRANSITIONS: [&[(char, char, usize)]; {}] = [", rows.len()).unwrap();
+    for row in rows {
+        let row: Vec<String> = row.iter().map(|(lo, hi, dest)| format!("({:?}, {:?}, {})", lo, hi, dest)).collect();
+        writeln!(out, "    &[{}],", row.join(", ")).unwrap();
+    }
+    *out += "];\n\n";
+    *out += "fn step(state: usize, c: char) -> Option<usize> {\n    TRANSITIONS[state]\n        .iter()\n        .find(|&&(lo, hi, _)| lo <= c && c <= hi)\n        .map(|&(_, _, dest)| dest)\n}\n";
+}
+
+fn emit_compressed_step(out: &mut String, rows: &[Vec<(char, char, usize)>]) {
+    let tables = compress(rows);
+    let int = if tables.int_size() == 2 { "u16" } else { "u32" };
+    let list = |values: &[usize]| -> String {
+        let values: Vec<String> = values
+            .iter()
+            .map(|&v| if v == tables.none { "NONE".to_string() } else { v.to_string() })
+            .collect();
+        values.join(", ")
+    };
+    writeln!(out, "const NONE: {} = {}::MAX;", int, int).unwrap();
+    *out += "// Clases de equivalencia: (desde, hasta, clase), ordenadas\n";
+    writeln!(out, "const CLASSES: [(char, char, {}); {}] = [", int, tables.classes.len()).unwrap();
+    for (lo, hi, class) in &tables.classes {
+        writeln!(out, "    ({:?}, {:?}, {}),", lo, hi, class).unwrap();
+    }
+    *out += "];\n// Filas empacadas: la fila de s empieza en BASE[s], lo que no está es DEFAULT[s]\n";
+    writeln!(out, "const BASE: [{}; {}] = [{}];", int, tables.base.len(), list(&tables.base)).unwrap();
+    writeln!(out, "const DEFAULT: [{}; {}] = [{}];", int, tables.default.len(), list(&tables.default)).unwrap();
+    writeln!(out, "const NEXT: [{}; {}] = [{}];", int, tables.next.len(), list(&tables.next)).unwrap();
+    writeln!(out, "const CHECK: [{}; {}] = [{}];", int, tables.check.len(), list(&tables.check)).unwrap();
+    writeln!(
+        out,
+        "
+fn step(state: usize, c: char) -> Option<usize> {{
+    let i = CLASSES.partition_point(|&(_, hi, _)| hi < c);
+    let &(lo, _, class) = CLASSES.get(i)?;
+    if c < lo {{
+        return None;
+    }}
+    let slot = BASE[state] as usize + class as usize;
+    let dest = if CHECK.get(slot) == Some(&(state as {int})) {{
+        NEXT[slot]
+    }} else {{
+        DEFAULT[state]
+    }};
+    (dest != NONE).then_some(dest as usize)
+}}",
+        int = int
+    )
+    .unwrap();
+}
+
+fn covers_all_chars(row: &[(char, char, usize)]) -> bool {
+    let covered: u32 = row
+        .iter()
+        .map(|&(lo, hi, _)| {
+            let (lo, hi) = (lo as u32, hi as u32);
+            // Los surrogates no son chars aunque caigan dentro del rango
+            let surrogates = (hi.min(0xDFFF) + 1).saturating_sub(lo.max(0xD800));
+            hi - lo + 1 - surrogates
+        })
+        .sum();
+    covered == 0x110000 - 0x800
+}
+
+// Cada estado es un brazo con un match sobre el caracter; los rangos que van al
+// mismo destino comparten brazo
+fn emit_direct_step(out: &mut String, rows: &[Vec<(char, char, usize)>]) {
+    *out += "fn step(state: usize, c: char) -> Option<usize> {\n    match state {\n";
+    for (state, row) in rows.iter().enumerate() {
+        if row.is_empty() {
+            continue;
+        }
+        let mut dests: Vec<usize> = row.iter().map(|&(_, _, dest)| dest).collect();
+        dests.sort();
+        dests.dedup();
+        writeln!(out, "        {} => match c {{", state).unwrap();
+        for dest in dests {
+            let patterns: Vec<String> = row
+                .iter()
+                .filter(|&&(_, _, d)| d == dest)
+                .map(|&(lo, hi, _)| {
+                    if lo == hi {
+                        format!("{:?}", lo)
+                    } else {
+                        format!("{:?}..={:?}", lo, hi)
+                    }
+                })
+                .collect();
+            writeln!(out, "            {} => Some({}),", patterns.join(" | "), dest).unwrap();
+        }
+        // Si el estado acepta cualquier caracter el brazo _ sobra
+        if !covers_all_chars(row) {
+            *out += "            _ => None,\n";
+        }
+        *out += "        },\n";
+    }
+    *out += "        _ => None,\n    }\n}\n";
+}
+
+// Parte fija del scanner generado
+const SCANNER: &str = r#"
+/// Tokens de la entrada con su lexema. Err con la posición (en caracteres)
+/// donde no empieza ningún token.
+pub fn scan(input: &str) -> Result<Vec<(TokenKind, String)>, usize> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let line_start = pos == 0 || chars[pos - 1] == '\n';
+        let mut state = if line_start { BOL_START } else { START };
+        // Última posición donde terminó la parte r de cada regla r/s
+        let mut marks: Vec<Option<usize>> = vec![None; RULES.len()];
+        let mut last: Option<(usize, usize, Option<usize>)> = None;
+        let mut read = 0;
+        loop {
+            for &rule in TRAIL[state] {
+                marks[rule] = Some(read);
+            }
+            if let Some(rule) = ACCEPT[state] {
+                last = Some((read, rule, marks[rule]));
+            }
+            match chars.get(pos + read).and_then(|&c| step(state, c)) {
+                Some(next) => state = next,
+                None => break,
+            }
+            read += 1;
+        }
+        let (len, rule) = match last {
+            Some((_, rule, Some(cut))) if cut > 0 => (cut, rule),
+            Some((len, rule, _)) if len > 0 => (len, rule),
+            _ => return Err(pos),
+        };
+        if let Some(kind) = RULES[rule] {
+            tokens.push((kind, chars[pos..pos + len].iter().collect()));
+        }
+        pos += len;
+    }
+    if let Some(kind) = EOF_TOKEN {
+        tokens.push((kind, String::new()));
+    }
+    Ok(tokens)
+}
+
+fn main() {
+    let mut input = String::new();
+    match std::env::args().nth(1) {
+        Some(path) => input = std::fs::read_to_string(path).expect("Cannot read input file"),
+        None => {
+            std::io::stdin().read_to_string(&mut input).expect("Cannot read stdin");
+        }
+    }
+    match scan(&input) {
+        Ok(tokens) => {
+            for (kind, lexeme) in tokens {
+                println!("{}\t{:?}", kind, lexeme);
+            }
+        }
+        Err(pos) => {
+            eprintln!("Token no identificado en la posición {}", pos);
+            std::process::exit(1);
+        }
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Generator;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::minimize::minimize_dfa;
+    use crate::spec_test;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_token_kind_enum() {
+        let actions: HashMap<usize, String> = [
+            (3, "tk_list.push(INT);"),
+            (4, "tk_list.push(L_BRACE);"),
+            (5, ""),
+            (6, "tk_list.push(INT);"),
+            (7, "L__BRACE"),
+            (8, "return5;"),
+        ]
+        .iter()
+        .map(|(line, action)| (*line, action.to_string()))
+        .collect();
+        let (kinds, by_line) = token_kinds(&[3, 4, 5, 6, 7, 8], &actions);
+        let variants: Vec<&str> = kinds.iter().map(|(v, _)| v.as_str()).collect();
+        assert_eq!(variants, vec!["Int", "LBrace", "LBrace7", "Return5"]);
+        assert_eq!(by_line[&6], "Int");
+        assert!(!by_line.contains_key(&5));
+
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(3, regex_parser::parse_rule("[0-9]+"));
+        builder.add_rule(4, regex_parser::parse_rule("\\{"));
+        builder.add_rule(5, regex_parser::parse_rule(" +"));
+        let dfa = minimize_dfa(&builder.build());
+        let scanner = emit_scanner("spec.yal", &dfa, &actions, None, Emission::Tables);
+        assert!(scanner.contains("pub enum TokenKind {\n    Int,\n    LBrace,\n}"));
+        assert!(scanner.contains("TokenKind::LBrace => \"L_BRACE\","));
+        assert!(scanner.contains("const RULES: [Option<TokenKind>; 3] = [\n    Some(TokenKind::Int),\n    Some(TokenKind::LBrace),\n    None,\n];"));
+
+        let direct = emit_scanner("spec.yal", &dfa, &actions, None, Emission::DirectCoded);
+        assert!(!direct.contains("TRANSITIONS"));
+        assert!(direct.contains("'0'..='9' => Some("));
+        // Fuera de step las dos salidas son iguales
+        let rest = |code: &str| code[code.find("pub fn scan").unwrap()..].to_string();
+        assert_eq!(rest(&scanner), rest(&direct));
+    }
+
+    // Compila un scanner generado con rustc y devuelve el ejecutable
+    fn build_scanner(code: &str, name: &str) -> PathBuf {
+        let dir = std::env::temp_dir();
+        let source = dir.join(format!("{}.rs", name));
+        let binary = dir.join(name);
+        fs::write(&source, code).unwrap();
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let status = Command::new(rustc)
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .status()
+            .expect("Cannot run rustc");
+        assert!(status.success(), "Generated scanner {} does not compile", name);
+        binary
+    }
+
+    // Salida completa de un scanner: tokens o el error con su posición
+    fn run_scanner(binary: &Path, input: &str) -> String {
+        let mut child = Command::new(binary)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+
+    #[test]
+    fn test_emitted_scanners_match_tables() {
+        let spec = "./test.yal";
+        let (rules, actions, eof_token) = gen_rules(spec);
+        let mut builder = LexerBuilder::new();
+        for (index, rule) in rules {
+            builder.add_rule(index, rule);
+        }
+        let dfa = minimize_dfa(&builder.build());
+        let tables = build_scanner(
+            &emit_scanner(spec, &dfa, &actions, eof_token, Emission::Tables),
+            "compile_test_tables",
+        );
+        let direct = build_scanner(
+            &emit_scanner(spec, &dfa, &actions, eof_token, Emission::DirectCoded),
+            "compile_test_direct",
+        );
+        let compressed = build_scanner(
+            &emit_scanner(spec, &dfa, &actions, eof_token, Emission::Compressed),
+            "compile_test_compressed",
+        );
+
+        // Casos del spec, entradas generadas y los bordes de cada rango del DFA,
+        // solos, después de un token y dentro de un comentario (rango _)
+        let mut inputs: Vec<String> = spec_test::inline_cases(spec).into_iter().map(|c| c.input).collect();
+        for seed in 0..20 {
+            let sequence = Generator::new(&dfa, seed).sequence(15, " ");
+            let lexemes: Vec<String> = sequence.into_iter().map(|(_, lexeme)| lexeme).collect();
+            inputs.push(lexemes.join(" "));
+        }
+        let mut bounds: Vec<char> = Vec::new();
+        for row in dfa.transitions.values() {
+            for key in row.keys() {
+                let (lo, hi) = key_range(key);
+                let below = (lo as u32).checked_sub(1).and_then(char::from_u32);
+                let above = char::from_u32(hi as u32 + 1);
+                bounds.extend([Some(lo), Some(hi), below, above].into_iter().flatten());
+            }
+        }
+        bounds.sort();
+        bounds.dedup();
+        for c in bounds {
+            inputs.push(c.to_string());
+            inputs.push(format!("x1{}", c));
+            inputs.push(format!("/*{}*/ 1..{}", c, c));
+            inputs.push(format!("\n#a{}", c));
+        }
+
+        let mut tokens = 0;
+        for input in &inputs {
+            let expected = run_scanner(&tables, input);
+            assert_eq!(run_scanner(&direct, input), expected, "direct input {:?}", input);
+            assert_eq!(run_scanner(&compressed, input), expected, "compressed input {:?}", input);
+            tokens += expected.lines().count();
+        }
+        assert!(tokens > inputs.len());
+    }
+}
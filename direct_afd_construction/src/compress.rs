@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use crate::alphabet::split_classes;
+
+// Tablas comprimidas para el scanner generado. Primero los caracteres se
+// agrupan en clases de equivalencia: dos rangos con la misma columna (mismo
+// destino desde cada estado) son la misma clase. Después cada fila de
+// estado × clase se reduce a una transición por defecto más las que difieren,
+// empacadas en un solo vector con desplazamiento por fila (comb vector):
+//     i = BASE[s] + clase;  si CHECK[i] == s entonces NEXT[i] si no DEFAULT[s]
+
+/// Tablas de un DFA con `none` como valor de "sin transición".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compressed {
+    /// (desde, hasta, clase) ordenados; los caracteres fuera no tienen clase
+    pub classes: Vec<(char, char, usize)>,
+    pub class_count: usize,
+    pub base: Vec<usize>,
+    pub default: Vec<usize>,
+    pub next: Vec<usize>,
+    pub check: Vec<usize>,
+    pub none: usize,
+}
+
+/// Tamaño en bytes de las tablas de transiciones con cada representación.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSizes {
+    /// Tabla [estado][caracter] sobre todo Unicode
+    pub dense: usize,
+    /// Rangos (desde, hasta, destino) por estado, como en Emission::Tables
+    pub ranges: usize,
+    pub compressed: usize,
+}
+
+fn lookup(row: &[(char, char, usize)], c: char) -> Option<usize> {
+    row.iter()
+        .find(|&&(lo, hi, _)| lo <= c && c <= hi)
+        .map(|&(_, _, dest)| dest)
+}
+
+/// Comprime las filas de transiciones (desde, hasta, destino) de cada estado.
+pub fn compress(rows: &[Vec<(char, char, usize)>]) -> Compressed {
+    let ranges: Vec<(char, char)> = rows.iter().flatten().map(|&(lo, hi, _)| (lo, hi)).collect();
+    let mut pieces = split_classes(&ranges);
+    pieces.sort();
+
+    // Columna de cada pedazo; pedazos con la misma columna comparten clase
+    let mut class_of_column: HashMap<Vec<Option<usize>>, usize> = HashMap::new();
+    let mut columns: Vec<Vec<Option<usize>>> = Vec::new();
+    let mut classes: Vec<(char, char, usize)> = Vec::new();
+    for (lo, hi) in pieces {
+        let column: Vec<Option<usize>> = rows.iter().map(|row| lookup(row, lo)).collect();
+        let class = *class_of_column.entry(column.clone()).or_insert_with(|| {
+            columns.push(column);
+            columns.len() - 1
+        });
+        match classes.last_mut() {
+            Some(last) if last.2 == class && last.1 as u32 + 1 == lo as u32 => last.1 = hi,
+            _ => classes.push((lo, hi, class)),
+        }
+    }
+
+    let none = usize::MAX;
+    let class_count = columns.len();
+    let mut default = Vec::with_capacity(rows.len());
+    let mut entries: Vec<Vec<(usize, usize)>> = Vec::with_capacity(rows.len());
+    for state in 0..rows.len() {
+        let row: Vec<usize> = columns
+            .iter()
+            .map(|column| column[state].unwrap_or(none))
+            .collect();
+        // La transición por defecto es la más común de la fila
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for &dest in &row {
+            *counts.entry(dest).or_insert(0) += 1;
+        }
+        let most_common = counts
+            .into_iter()
+            .max_by_key(|&(dest, count)| (count, dest == none, std::cmp::Reverse(dest)))
+            .map_or(none, |(dest, _)| dest);
+        default.push(most_common);
+        entries.push(
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &dest)| dest != most_common)
+                .map(|(class, &dest)| (class, dest))
+                .collect(),
+        );
+    }
+
+    // Primero las filas con más entradas, cada una en el primer hueco que le sirve
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|&state| std::cmp::Reverse(entries[state].len()));
+    let mut base = vec![0; rows.len()];
+    let mut next: Vec<usize> = Vec::new();
+    let mut check: Vec<usize> = Vec::new();
+    for state in order {
+        if entries[state].is_empty() {
+            continue;
+        }
+        let fits = |offset: usize| {
+            entries[state]
+                .iter()
+                .all(|&(class, _)| check.get(offset + class).is_none_or(|&owner| owner == none))
+        };
+        let offset = (0..).find(|&offset| fits(offset)).unwrap();
+        for &(class, dest) in &entries[state] {
+            let slot = offset + class;
+            if slot >= next.len() {
+                next.resize(slot + 1, none);
+                check.resize(slot + 1, none);
+            }
+            next[slot] = dest;
+            check[slot] = state;
+        }
+        base[state] = offset;
+    }
+
+    Compressed {
+        classes,
+        class_count,
+        base,
+        default,
+        next,
+        check,
+        none,
+    }
+}
+
+impl Compressed {
+    /// Bytes por entrada en el código generado: u16 si todo cabe, si no u32
+    pub fn int_size(&self) -> usize {
+        let max = [self.class_count, self.base.len(), self.next.len()]
+            .into_iter()
+            .chain(self.base.iter().cloned())
+            .max()
+            .unwrap_or(0);
+        if max < u16::MAX as usize {
+            2
+        } else {
+            4
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        let int = self.int_size();
+        self.classes.len() * (4 + 4 + int)
+            + (self.base.len() + self.default.len() + self.next.len() + self.check.len()) * int
+    }
+}
+
+/// Tamaños de las tablas antes y después de comprimir.
+pub fn table_sizes(rows: &[Vec<(char, char, usize)>]) -> TableSizes {
+    let compressed = compress(rows);
+    let int = compressed.int_size();
+    // Chars válidos: todo el rango menos los surrogates
+    let chars = 0x110000 - 0x800;
+    TableSizes {
+        dense: rows.len() * chars * int,
+        ranges: rows.iter().map(|row| row.len() * (4 + 4 + 8)).sum(),
+        compressed: compressed.size(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::{gen_rules, scanner_table_sizes};
+    use crate::utilities::generate;
+
+    // La misma búsqueda que hace el scanner generado
+    fn step(tables: &Compressed, state: usize, c: char) -> Option<usize> {
+        let i = tables.classes.partition_point(|&(_, hi, _)| hi < c);
+        let class = tables.classes.get(i).filter(|&&(lo, _, _)| lo <= c)?.2;
+        let slot = tables.base[state] + class;
+        let dest = if tables.check.get(slot) == Some(&state) {
+            tables.next[slot]
+        } else {
+            tables.default[state]
+        };
+        (dest != tables.none).then_some(dest)
+    }
+
+    #[test]
+    fn test_compressed_tables_keep_transitions() {
+        // 0: [a-z] -> 1, [0-9] -> 2;  1: [a-z0-9] -> 1;  2: [0-9] -> 2, . -> 3;  3: todo menos \n -> 3
+        let rows = vec![
+            vec![('0', '9', 2), ('a', 'z', 1)],
+            vec![('0', '9', 1), ('a', 'z', 1)],
+            vec![('.', '.', 3), ('0', '9', 2)],
+            vec![('\0', '\t', 3), ('\u{b}', char::MAX, 3)],
+        ];
+        let tables = compress(&rows);
+        // Dígitos, letras, el punto y el resto; el \n no tiene clase
+        assert_eq!(tables.class_count, 4);
+        let chars = [
+            '\0',
+            '\n',
+            '.',
+            '/',
+            '0',
+            '9',
+            'a',
+            'q',
+            'z',
+            '{',
+            'ñ',
+            char::MAX,
+        ];
+        for (state, row) in rows.iter().enumerate() {
+            for c in chars {
+                assert_eq!(
+                    step(&tables, state, c),
+                    lookup(row, c),
+                    "state {} char {:?}",
+                    state,
+                    c
+                );
+            }
+        }
+
+        // Con un spec de verdad las clases se pagan solas
+        let (rules, _, _) = gen_rules("./test.yal");
+        let sizes = scanner_table_sizes(&generate(rules));
+        assert!(sizes.compressed * 2 < sizes.ranges && sizes.ranges < sizes.dense);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::alphabet::{class_key, split_classes};
+use crate::dfa::Dfa;
+use crate::grammar_tree::{Tree, TreeNode};
+use crate::inf_to_pos::Token;
+use crate::utilities::next_state_name;
+
+// Construcción por derivadas de Brzozowski. Cada estado del DFA es una regex
+// normalizada: uniones e intersecciones aplanadas, ordenadas y sin repetidos,
+// así que solo hay un número finito de derivadas distintas.
+//
+// Los marcadores de token y de r/s son hojas de ancho cero: son anulables y su
+// derivada es ∅. Un estado acepta el token (o marca el r/s) cuyo marcador puede
+// ser lo siguiente en la regex, ver `ready`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Re {
+    Null,
+    Eps,
+    Class(char, char),
+    Bol,
+    Trail(String),
+    Token(String),
+    Concat(Box<Re>, Box<Re>),
+    Star(Box<Re>),
+    Or(Vec<Re>),
+    And(Vec<Re>),
+    Not(Box<Re>),
+}
+
+pub fn concat(a: Re, b: Re) -> Re {
+    match (a, b) {
+        (Re::Null, _) | (_, Re::Null) => Re::Null,
+        (Re::Eps, b) => b,
+        (a, Re::Eps) => a,
+        // (xy)z = x(yz)
+        (Re::Concat(x, y), b) => concat(*x, concat(*y, b)),
+        (a, b) => Re::Concat(Box::new(a), Box::new(b)),
+    }
+}
+
+pub fn star(a: Re) -> Re {
+    match a {
+        Re::Null | Re::Eps => Re::Eps,
+        Re::Star(_) => a,
+        a => Re::Star(Box::new(a)),
+    }
+}
+
+pub fn or(items: Vec<Re>) -> Re {
+    let mut flat = Vec::new();
+    for item in items {
+        match item {
+            Re::Or(inner) => flat.extend(inner),
+            Re::Null => {}
+            item => flat.push(item),
+        }
+    }
+    let everything = not(Re::Null);
+    if flat.contains(&everything) {
+        return everything;
+    }
+    flat.sort();
+    flat.dedup();
+    match flat.len() {
+        0 => Re::Null,
+        1 => flat.pop().unwrap(),
+        _ => Re::Or(flat),
+    }
+}
+
+pub fn and(items: Vec<Re>) -> Re {
+    let everything = not(Re::Null);
+    let mut flat = Vec::new();
+    for item in items {
+        match item {
+            Re::And(inner) => flat.extend(inner),
+            Re::Null => return Re::Null,
+            item if item == everything => {}
+            item => flat.push(item),
+        }
+    }
+    flat.sort();
+    flat.dedup();
+    match flat.len() {
+        0 => everything,
+        1 => flat.pop().unwrap(),
+        _ => Re::And(flat),
+    }
+}
+
+pub fn not(a: Re) -> Re {
+    match a {
+        Re::Not(inner) => *inner,
+        a => Re::Not(Box::new(a)),
+    }
+}
+
+impl Re {
+    pub fn from_node(node: &TreeNode) -> Re {
+        let left = || Re::from_node(&node.get_left().unwrap());
+        let right = || Re::from_node(&node.get_right().unwrap());
+        match node.get_value() {
+            Token::Literal(c) => Re::Class(*c, *c),
+            Token::Range(start, end) => Re::Class(*start, *end),
+            Token::Tokener(id) => Re::Token(id.clone()),
+            Token::Trail(id) => Re::Trail(id.clone()),
+            Token::Bol => Re::Bol,
+            Token::Sentinel | Token::Empty => Re::Eps,
+            Token::Concat => concat(left(), right()),
+            Token::Union => or(vec![left(), right()]),
+            Token::Kleene => star(left()),
+            Token::Plus => {
+                let inner = left();
+                concat(inner.clone(), star(inner))
+            }
+            Token::Optional => or(vec![Re::Eps, left()]),
+            Token::And => and(vec![left(), right()]),
+            Token::Not => not(left()),
+            other => panic!("Unexpected token {:?} in syntax tree", other),
+        }
+    }
+
+    pub fn nullable(&self) -> bool {
+        match self {
+            Re::Null | Re::Class(_, _) | Re::Bol => false,
+            Re::Eps | Re::Trail(_) | Re::Token(_) | Re::Star(_) => true,
+            Re::Concat(a, b) => a.nullable() && b.nullable(),
+            Re::Or(items) => items.iter().any(|r| r.nullable()),
+            Re::And(items) => items.iter().all(|r| r.nullable()),
+            Re::Not(a) => !a.nullable(),
+        }
+    }
+
+    pub fn derive(&self, c: char) -> Re {
+        match self {
+            Re::Null | Re::Eps | Re::Bol | Re::Trail(_) | Re::Token(_) => Re::Null,
+            Re::Class(start, end) => {
+                if *start <= c && c <= *end {
+                    Re::Eps
+                } else {
+                    Re::Null
+                }
+            }
+            Re::Concat(a, b) => {
+                let first = concat(a.derive(c), (**b).clone());
+                if a.nullable() {
+                    or(vec![first, b.derive(c)])
+                } else {
+                    first
+                }
+            }
+            Re::Star(a) => concat(a.derive(c), self.clone()),
+            Re::Or(items) => or(items.iter().map(|r| r.derive(c)).collect()),
+            Re::And(items) => and(items.iter().map(|r| r.derive(c)).collect()),
+            Re::Not(a) => not(a.derive(c)),
+        }
+    }
+
+    /// Marcadores que pueden venir a continuación sin consumir nada:
+    /// los de token dicen qué reglas aceptan y los de r/s dónde termina r.
+    fn ready(&self, tokens: &mut HashSet<String>, trails: &mut HashSet<String>) {
+        match self {
+            Re::Token(id) => {
+                tokens.insert(id.clone());
+            }
+            Re::Trail(id) => {
+                trails.insert(id.clone());
+            }
+            Re::Concat(a, b) => {
+                a.ready(tokens, trails);
+                if a.nullable() {
+                    b.ready(tokens, trails);
+                }
+            }
+            Re::Star(a) => a.ready(tokens, trails),
+            Re::Or(items) => items.iter().for_each(|r| r.ready(tokens, trails)),
+            _ => {}
+        }
+    }
+
+    // ^ vale ε al inicio de línea y ∅ en cualquier otra parte
+    fn at_line_start(&self, inicio_de_linea: bool) -> Re {
+        match self {
+            Re::Bol if inicio_de_linea => Re::Eps,
+            Re::Bol => Re::Null,
+            Re::Concat(a, b) => concat(
+                a.at_line_start(inicio_de_linea),
+                b.at_line_start(inicio_de_linea),
+            ),
+            Re::Star(a) => star(a.at_line_start(inicio_de_linea)),
+            Re::Or(items) => or(items.iter().map(|r| r.at_line_start(inicio_de_linea)).collect()),
+            Re::And(items) => and(items.iter().map(|r| r.at_line_start(inicio_de_linea)).collect()),
+            Re::Not(a) => not(a.at_line_start(inicio_de_linea)),
+            other => other.clone(),
+        }
+    }
+
+    fn visit(&self, f: &mut dyn FnMut(&Re)) {
+        f(self);
+        match self {
+            Re::Concat(a, b) => {
+                a.visit(f);
+                b.visit(f);
+            }
+            Re::Star(a) | Re::Not(a) => a.visit(f),
+            Re::Or(items) | Re::And(items) => items.iter().for_each(|r| r.visit(f)),
+            _ => {}
+        }
+    }
+}
+
+fn collect_tokens(node: &TreeNode, token_list: &mut Vec<String>) {
+    if let Some(left) = node.get_left() {
+        collect_tokens(&left, token_list);
+    }
+    if let Some(right) = node.get_right() {
+        collect_tokens(&right, token_list);
+    }
+    if let Token::Tokener(id) = node.get_value() {
+        token_list.push(id.clone());
+    }
+}
+
+/// DFA por derivadas del árbol del lexer, con los estados nombrados desde 'A'.
+pub fn derivative_dfa(tree: &Tree) -> Dfa {
+    let root_node = tree.get_root().expect("The syntax tree is empty");
+    let mut token_list = Vec::new();
+    collect_tokens(&root_node, &mut token_list);
+    let root = Re::from_node(&root_node);
+
+    // Las derivadas nunca crean clases nuevas; con complemento hay que cubrir
+    // también los caracteres que no aparecen en ninguna hoja
+    let mut ranges = Vec::new();
+    let mut has_bol = false;
+    root.visit(&mut |r| match r {
+        Re::Class(start, end) => ranges.push((*start, *end)),
+        Re::Not(_) => ranges.push(('\0', char::MAX)),
+        Re::Bol => has_bol = true,
+        _ => {}
+    });
+    let mut classes = split_classes(&ranges);
+    classes.sort();
+
+    let mut dfa = Dfa::new('A', token_list.clone());
+    let mut names: HashMap<Re, char> = HashMap::new();
+    let mut pending: VecDeque<Re> = VecDeque::new();
+    let mut state_letter = 'A';
+
+    let start = root.at_line_start(false);
+    names.insert(start.clone(), state_letter);
+    pending.push_back(start);
+    if has_bol {
+        let bol_start = root.at_line_start(true);
+        let bol_name = match names.get(&bol_start) {
+            Some(&name) => name,
+            None => {
+                state_letter = next_state_name(state_letter);
+                names.insert(bol_start.clone(), state_letter);
+                pending.push_back(bol_start);
+                state_letter
+            }
+        };
+        dfa.bol_start = Some(bol_name);
+    }
+
+    while let Some(re) = pending.pop_front() {
+        let name = names[&re];
+
+        let mut tokens = HashSet::new();
+        let mut trails = HashSet::new();
+        re.ready(&mut tokens, &mut trails);
+        if let Some(winner) = token_list.iter().find(|t| tokens.contains(*t)) {
+            dfa.accept.insert(name, winner.clone());
+        }
+        if !trails.is_empty() {
+            dfa.trail.insert(name, trails);
+        }
+
+        let mut row = HashMap::new();
+        for &(start, end) in &classes {
+            // Todos los caracteres de una clase dan la misma derivada
+            let next = re.derive(start);
+            if next == Re::Null {
+                continue;
+            }
+            let dest = match names.get(&next) {
+                Some(&dest) => dest,
+                None => {
+                    state_letter = next_state_name(state_letter);
+                    names.insert(next.clone(), state_letter);
+                    pending.push_back(next);
+                    state_letter
+                }
+            };
+            row.insert(class_key(start, end), dest);
+        }
+        dfa.transitions.insert(name, row);
+    }
+    dfa
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::isomorphic;
+    use crate::lexer_builder::{Construction, LexerBuilder};
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+
+    fn matches(re: &Re, input: &str) -> bool {
+        input.chars().fold(re.clone(), |r, c| r.derive(c)).nullable()
+    }
+
+    #[test]
+    fn test_derivatives_match_direct() {
+        let patterns = [
+            "while",
+            "[a-z]([a-z]|[0-9])*",
+            "-?[0-9]+(\\.[0-9]*)?",
+            "[0-9]+/\\.\\.",
+            "\\.\\.",
+            "^#[a-z]+",
+            "(ab|a)*b?",
+        ];
+        // (ab|a)*b? acepta ε, se permite para probar ese caso
+        let mut builder = LexerBuilder::new();
+        builder.allow_empty(true);
+        for (index, pattern) in patterns.iter().enumerate() {
+            builder.add_rule(index, parse_rule(pattern));
+        }
+        let direct = minimize_dfa(&builder.build());
+        builder.construction(Construction::Derivative);
+        let derived = minimize_dfa(&builder.build());
+        assert!(isomorphic(&direct, &derived));
+    }
+
+    #[test]
+    fn test_intersection_and_complement() {
+        // identificadores que no son la palabra reservada "if"
+        let ident = Re::from_node(&parse_rule("[a-z]+"));
+        let keyword = Re::from_node(&parse_rule("if"));
+        let re = and(vec![ident, not(keyword)]);
+        assert!(matches(&re, "i"));
+        assert!(matches(&re, "iff"));
+        assert!(!matches(&re, "if"));
+        assert!(!matches(&re, "i9"));
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::alphabet::key_matches;
+
+/// Autómata del lexer. Las transiciones van por clases de caracteres disjuntas
+/// (ver `alphabet`); los marcadores de token y de r/s no son símbolos, quedan
+/// como atributos de los estados.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dfa {
+    pub start: char,
+    pub transitions: HashMap<char, HashMap<String, char>>,
+    /// Estado de aceptación -> token de la regla con más prioridad
+    pub accept: HashMap<char, String>,
+    /// Estado -> tokens cuya parte r de r/s termina en ese estado
+    pub trail: HashMap<char, HashSet<String>>,
+    /// Estado inicial al inicio de línea, solo si hay reglas con ^
+    pub bol_start: Option<char>,
+    /// Tokens en orden de prioridad
+    pub token_list: Vec<String>,
+}
+
+impl Dfa {
+    pub fn new(start: char, token_list: Vec<String>) -> Self {
+        Self {
+            start,
+            transitions: HashMap::new(),
+            accept: HashMap::new(),
+            trail: HashMap::new(),
+            bol_start: None,
+            token_list,
+        }
+    }
+
+    pub fn step(&self, state: char, c: char) -> Option<char> {
+        self.transitions
+            .get(&state)?
+            .iter()
+            .find(|(key, _)| key_matches(key, c))
+            .map(|(_, &dest)| dest)
+    }
+
+    pub fn step_key(&self, state: char, key: &str) -> Option<char> {
+        self.transitions.get(&state)?.get(key).copied()
+    }
+
+    /// Copia sin estados inalcanzables desde los iniciales ni estados muertos
+    /// (los que ya no llegan a aceptar). Los iniciales se conservan siempre.
+    pub fn prune(&self) -> Dfa {
+        let starts: Vec<char> = [self.start].into_iter().chain(self.bol_start).collect();
+        let mut reachable: HashSet<char> = starts.iter().cloned().collect();
+        let mut pendientes: VecDeque<char> = starts.iter().cloned().collect();
+        while let Some(state) = pendientes.pop_front() {
+            for &dest in self.transitions.get(&state).into_iter().flat_map(|row| row.values()) {
+                if reachable.insert(dest) {
+                    pendientes.push_back(dest);
+                }
+            }
+        }
+
+        // Vivos: los que aceptan y los que llegan a uno que acepta
+        let mut live: HashSet<char> = self.accept.keys().cloned().collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&state, row) in &self.transitions {
+                if !live.contains(&state) && row.values().any(|dest| live.contains(dest)) {
+                    live.insert(state);
+                    changed = true;
+                }
+            }
+        }
+
+        let keep = |state: &char| reachable.contains(state) && live.contains(state);
+        let mut pruned = Dfa::new(self.start, self.token_list.clone());
+        pruned.bol_start = self.bol_start;
+        for &state in &reachable {
+            if !keep(&state) && !starts.contains(&state) {
+                continue;
+            }
+            let row: HashMap<String, char> = self
+                .transitions
+                .get(&state)
+                .into_iter()
+                .flatten()
+                .filter(|(_, dest)| keep(dest))
+                .map(|(key, &dest)| (key.clone(), dest))
+                .collect();
+            pruned.transitions.insert(state, row);
+            if let Some(token) = self.accept.get(&state) {
+                pruned.accept.insert(state, token.clone());
+            }
+            if let Some(trail) = self.trail.get(&state) {
+                pruned.trail.insert(state, trail.clone());
+            }
+        }
+        pruned
+    }
+}
+
+/// Compara dos DFAs recorriéndolos a la vez desde sus estados iniciales.
+/// Son isomorfos si la correspondencia de estados respeta transiciones,
+/// tokens aceptados, marcas de r/s y el inicio de línea.
+pub fn isomorphic(a: &Dfa, b: &Dfa) -> bool {
+    let mut mapping: HashMap<char, char> = HashMap::new();
+    let mut pendientes = vec![(a.start, b.start)];
+    if let (Some(a_bol), Some(b_bol)) = (a.bol_start, b.bol_start) {
+        pendientes.push((a_bol, b_bol));
+    } else if a.bol_start.is_some() != b.bol_start.is_some() {
+        return false;
+    }
+    while let Some((sa, sb)) = pendientes.pop() {
+        match mapping.get(&sa) {
+            Some(&mapped) if mapped == sb => continue,
+            Some(_) => return false,
+            None => {}
+        }
+        // La correspondencia tiene que ser biyectiva
+        if mapping.values().any(|&v| v == sb) {
+            return false;
+        }
+        mapping.insert(sa, sb);
+        if a.accept.get(&sa) != b.accept.get(&sb) || a.trail.get(&sa) != b.trail.get(&sb) {
+            return false;
+        }
+        let empty = HashMap::new();
+        let row_a = a.transitions.get(&sa).unwrap_or(&empty);
+        let row_b = b.transitions.get(&sb).unwrap_or(&empty);
+        if row_a.len() != row_b.len() {
+            return false;
+        }
+        for (key, &dest_a) in row_a {
+            match row_b.get(key) {
+                Some(&dest_b) => pendientes.push((dest_a, dest_b)),
+                None => return false,
+            }
+        }
+    }
+    true
+}
@@ -1,10 +1,13 @@
 use std::clone;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 use std::collections::BTreeMap;
 
+use crate::alphabet::{class_key, split_classes};
+use crate::dfa::Dfa;
 use crate::grammar_tree::{Tree, TreeNode};
 use crate::inf_to_pos::Token;
+use crate::utilities::next_state_name;
 
 pub struct DirectAFD {
     syntax_tree: Rc<Tree>,
@@ -23,147 +26,27 @@ impl DirectAFD {
         }
     }
 
-    pub fn generate_afd(&mut self) {
-        self.read_tree();
-        self.find_nullable();
-        self.find_first_last_pos();
-        self.find_followpos();
-        self.create_states();
+    // create_states ya calcula nullable, firstpos/lastpos y followpos
+    pub fn generate_afd(&mut self) -> Dfa {
+        self.create_states()
     }
 
     // Lee el árbol y guarda sus labels
     pub fn read_tree(&self) -> (HashMap<String, String>, String, Vec<String>) {
-        let mut labels = HashMap::new();
-        let mut literal_count = 1;
-        let mut union_count = 1;
-        let mut kleene_count = 1;
-        let mut concat_count = 1;
+        let mut labeler = Labeler::default();
         let mut root_key = String::new();
-        let mut token_list = Vec::new();
-
-        // Función recursiva que recorre el árbol y asigna etiquetas
-        fn traverse(
-            node: &TreeNode,
-            labels: &mut HashMap<String, String>,
-            literal_count: &mut usize,
-            union_count: &mut usize,
-            kleene_count: &mut usize,
-            concat_count: &mut usize,
-            token_list: &mut Vec<String>,
-        ) -> String {
-            // println!("Visitando nodo: {:?}", node.get_value());
-
-            // Obtener los identificadores de los hijos (si existen)
-            let left_id = node.get_left().map(|left| {
-                let id = traverse(
-                    &left,
-                    labels,
-                    literal_count,
-                    union_count,
-                    kleene_count,
-                    concat_count,
-                    token_list,
-                );
-                // println!("Nodo izquierdo: {:?} -> ID: {:?}", left.get_value(), id);
-                id
-            });
-            let right_id = node.get_right().map(|right| {
-                let id = traverse(
-                    &right,
-                    labels,
-                    literal_count,
-                    union_count,
-                    kleene_count,
-                    concat_count,
-                    token_list,
-                );
-                // println!("Nodo izquierdo: {:?} -> ID: {:?}", right.get_value(), id);
-                id
-            });
-
-            // Asignar identificador al nodo actual
-            let node_id = match node.get_value() {
-                Token::Literal(c) => {
-                    let id = literal_count.to_string();
-                    labels.insert(id.clone(), format!("Literal('{}')", c));
-                    *literal_count += 1;
-                    id
-                }
-                Token::Range(c, d) => {
-                    let id = literal_count.to_string();
-                    labels.insert(id.clone(), format!("Range('{},{}')", c, d));
-                    *literal_count += 1;
-                    id
-                }
-                Token::Tokener(c,) => {
-                    let id = literal_count.to_string();
-                    labels.insert(id.clone(), format!("Token('{}')", c));
-                    token_list.push(c.to_string());
-                    *literal_count += 1;
-                    id
-                }
-                Token::Union => {
-                    let id = format!("alpha{}", *union_count);
-                    *union_count += 1;
-                    if let (Some(c1), Some(c2)) = (left_id.clone(), right_id.clone()) {
-                        labels.insert(id.clone(), format!("({}, {})", c1, c2));
-                    }
-                    id
-                }
-                Token::Kleene => {
-                    let id = format!("beta{}", *kleene_count);
-                    *kleene_count += 1;
-                    if let Some(c1) = left_id.clone() {
-                        labels.insert(id.clone(), format!("({})", c1));
-                    }
-                    id
-                }
-                Token::Concat => {
-                    let id = format!("gama{}", *concat_count);
-                    *concat_count += 1;
-                    if let (Some(c1), Some(c2)) = (left_id.clone(), right_id.clone()) {
-                        labels.insert(id.clone(), format!("({}, {})", c1, c2));
-                    }
-                    id
-                }
-                Token::Sentinel => {
-                    let id = literal_count.to_string();
-                    labels.insert(id.clone(), "Sentinel".to_string());
-                    *literal_count += 1;
-                    id
-                }
-                Token::Empty => {
-                    let id = "empty".to_string();
-                    labels.insert(id.clone(), "Empty".to_string());
-                    id
-                }
-                _ => unreachable!("Unexpected token type in syntax tree"),
-            };
-
-            // println!("Asignando etiquieta: {} -> {:?}", node_id, labels.get(&node_id));
-
-            node_id
-        }
 
         // Llamar a la función de recorrido desde la raíz
         if let Some(root_node) = self.syntax_tree.get_root() {
             // println!("Iniciando recorrido desde la raíz");
             // Realizamos el recorrido y asignamos las etiquetas
-            root_key = traverse(
-                &root_node,
-                &mut labels,
-                &mut literal_count,
-                &mut union_count,
-                &mut kleene_count,
-                &mut concat_count,
-                &mut token_list,
-            );
+            root_key = labeler.traverse(&root_node);
 
             // println!("Árbol etiquetado: {:?}", labels);
             // println!("Clave raíz: {}", root_key);
         }
 
-        (labels, root_key, token_list)
+        (labeler.labels, root_key, labeler.token_list)
     }
 
     pub fn find_nullable(&self) -> HashMap<String, bool> {
@@ -188,6 +71,8 @@ impl DirectAFD {
             } else if value.starts_with("Token") {
                 nullable_map.insert(key.clone(), false);
                 // println!("Inicializando {} como false (Token)", key);
+            } else if value.starts_with("Trail") || value.starts_with("Bol") {
+                nullable_map.insert(key.clone(), false);
             }
         }
 
@@ -200,10 +85,16 @@ impl DirectAFD {
             for (key, value) in &tree_map {
                 let original_nullable = nullable_map.get(key).cloned();
 
-                if key.starts_with("beta") {
-                    // Kleene (beta): El valor siempre es true
+                if key.starts_with("beta") || key.starts_with("theta") {
+                    // Kleene (beta) y opcional (theta): El valor siempre es true
                     nullable_map.insert(key.clone(), true);
                     // println!("{} es Kleene, siempre true", key);
+                } else if key.starts_with("delta") {
+                    // Plus (delta): nullable solo si su hijo lo es
+                    if let Some(c1) = extract_single_child(value) {
+                        let nullable_c1 = *nullable_map.get(&c1).unwrap_or(&false);
+                        nullable_map.insert(key.clone(), nullable_c1);
+                    }
                 } else if key.starts_with("gama") {
                     if let Some((c1, c2)) = extract_children(value) {
                         let nullable_c1 = *nullable_map.get(&c1).unwrap_or(&false);
@@ -248,14 +139,22 @@ impl DirectAFD {
         nullable_map
     }
 
+    // nullable de la raíz: el árbol acepta la cadena vacía
+    pub fn root_nullable(&self) -> bool {
+        let (_tree_map, root_key, _token_list) = self.read_tree();
+        *self.find_nullable().get(&root_key).unwrap_or(&false)
+    }
+
     pub fn find_first_last_pos(&self,) -> (HashMap<String, Vec<String>>, HashMap<String, Vec<String>>) {
         let (tree_map, _key, _token_list) = self.read_tree();
         let mut firstpos_map: HashMap<String, Vec<String>> = HashMap::new();
         let mut lastpos_map: HashMap<String, Vec<String>> = HashMap::new();
+        // Nullable no cambia durante la fijación, se calcula una sola vez
+        let nullable_map = self.find_nullable();
 
         // Primera pasada: Inicializar Literales
         for (key, value) in &tree_map {
-            if value.starts_with("Literal") || value.starts_with("Sentinel") || value.starts_with("Range") || value.starts_with("Token"){
+            if value.starts_with("Literal") || value.starts_with("Sentinel") || value.starts_with("Range") || value.starts_with("Token") || value.starts_with("Trail") || value.starts_with("Bol"){
                 // Para Literals, firstpos y lastpos es solo su propia key
                 firstpos_map.insert(key.clone(), vec![key.clone()]);
                 lastpos_map.insert(key.clone(), vec![key.clone()]);
@@ -273,8 +172,8 @@ impl DirectAFD {
                 let original_firstpos = firstpos_map.get(key).cloned();
                 let original_lastpos = lastpos_map.get(key).cloned();
 
-                if key.starts_with("beta") {
-                    // Kleene (beta): Igualar firstpos y lastpos al nodo al que está conectado
+                if key.starts_with("beta") || key.starts_with("delta") || key.starts_with("theta") {
+                    // Kleene (beta), plus (delta) y opcional (theta): Igualar firstpos y lastpos al nodo al que está conectado
                     if let Some(c1) = extract_single_child(value) {
                         let firstpos = firstpos_map.get(&c1).cloned().unwrap_or_default();
                         let lastpos = lastpos_map.get(&c1).cloned().unwrap_or_default();
@@ -310,8 +209,8 @@ impl DirectAFD {
                         let lastpos_c1 = lastpos_map.get(&c1).cloned().unwrap_or_default();
                         let lastpos_c2 = lastpos_map.get(&c2).cloned().unwrap_or_default();
 
-                        let nullable_c1 = *self.find_nullable().get(&c1).unwrap_or(&false);
-                        let nullable_c2 = *self.find_nullable().get(&c2).unwrap_or(&false);
+                        let nullable_c1 = *nullable_map.get(&c1).unwrap_or(&false);
+                        let nullable_c2 = *nullable_map.get(&c2).unwrap_or(&false);
 
                         // Firstpos: Si el izquierdo es nullable, hacer unión con el derecho
                         let firstpos = if nullable_c1 {
@@ -406,8 +305,8 @@ impl DirectAFD {
                         }
                     }
                 }
-            } else if key.starts_with("beta") {
-                // Kleene (beta): Followpos de lastpos del nodo es firstpos del mismo nodo
+            } else if key.starts_with("beta") || key.starts_with("delta") {
+                // Kleene (beta) y plus (delta): Followpos de lastpos del nodo es firstpos del mismo nodo
                 if let Some(c1) = extract_single_child(value) {
                     if let Some(lastpos_c1) = lastpos_map.get(&c1) {
                         if let Some(firstpos_c1) = firstpos_map.get(&c1) {
@@ -426,15 +325,15 @@ impl DirectAFD {
 
         // Asegurar que todos los literales y sentinels tengan followpos, aunque sea vacío
         for (key, value) in &tree_map {
-            if value.starts_with("Literal") || value.starts_with("Sentinel") || value.starts_with("Range") || value.starts_with("Token") {
-                followpos_map.entry(key.clone()).or_insert(Vec::new());
+            if value.starts_with("Literal") || value.starts_with("Sentinel") || value.starts_with("Range") || value.starts_with("Token") || value.starts_with("Trail") || value.starts_with("Bol") {
+                followpos_map.entry(key.clone()).or_default();
             }
         }
 
         // Filtrar y eliminar los nodos que no sean Sentinel o Literal
         followpos_map.retain(|key, _| {
             if let Some(value) = tree_map.get(key) {
-                value.starts_with("Literal") || value.starts_with("Sentinel") || value.starts_with("Range") || value.starts_with("Token")
+                value.starts_with("Literal") || value.starts_with("Sentinel") || value.starts_with("Range") || value.starts_with("Token") || value.starts_with("Trail") || value.starts_with("Bol")
             } else {
                 false
             }
@@ -462,151 +361,296 @@ impl DirectAFD {
         followpos_map
     }
 
-    pub fn create_states(&mut self) -> (HashMap<char, HashMap<String, char>>, HashSet<char>, Vec<String>) {
-        let mut state_map: HashMap<char, HashMap<String, char>> = HashMap::new(); // Mapa de estados y sus transiciones
-        let mut acceptance_states: HashSet<char> = HashSet::new(); // Lista de estados de aceptación
-        let mut state_queue: HashMap<String, Vec<String>> = HashMap::new(); // Cola de estados por procesar
-        let mut fake_state_queue: HashMap<String, Vec<String>> = HashMap::new();
-        let mut visited_states: HashMap<String, Vec<String>> = HashMap::new(); // Para evitar procesar estados duplicados
-        let mut state_letter = 'A';
-
-        // Obtener el firstpos del nodo raíz
-        let (mut labels_map, root_key, token_list) = self.read_tree();
+    pub fn create_states(&mut self) -> Dfa {
+        let (labels_map, root_key, token_list) = self.read_tree();
         let root_firstpos = self
             .find_first_last_pos()
             .0
             .get(&root_key)
-            .unwrap_or(&Vec::new())
-            .clone();
-
-        // println!("Root FirstPos: {:?}", root_firstpos);
-
-        state_queue.insert(state_letter.to_string(), root_firstpos.clone());
-        fake_state_queue.insert(state_letter.to_string(), root_firstpos.clone());
-
+            .cloned()
+            .unwrap_or_default();
         let followpos_map = self.find_followpos();
 
-        labels_map.retain(|key, _| followpos_map.contains_key(key));
-
-        // println!("Labels Map after retaining: {:?}", labels_map);
-
-        let mut columns: HashSet<String> = HashSet::new();
-        for (_key, value) in &labels_map {
-            if value.starts_with("Literal") || value.starts_with("Range") || value.starts_with("Token") {
-                if let Some(start) = value.find('\'') {
-                    if let Some(end) = value[start + 1..].find('\'') {
-                        let extracted = &value[start + 1..start + 1 + end];
-                        columns.insert(extracted.to_string()); // Insertar el valor extraído en el HashSet
+        // Solo las literales y rangos consumen entrada; los marcadores son atributos
+        let mut ranges: HashMap<String, (char, char)> = HashMap::new();
+        for key in followpos_map.keys() {
+            if let Some(range) = labels_map.get(key).and_then(|l| leaf_range(l)) {
+                ranges.insert(key.clone(), range);
+            }
+        }
+        let all_ranges: Vec<(char, char)> = ranges.values().cloned().collect();
+        let mut classes = split_classes(&all_ranges);
+        classes.sort();
+
+        let label_of = |pos: &String| labels_map.get(pos).map(|l| l.as_str()).unwrap_or("");
+
+        // El fin de r en r/s no consume nada: se siguen sus followpos en el mismo estado
+        let closure = |set: Vec<String>| -> Vec<String> {
+            let mut closed = set.clone();
+            let mut pendientes = set;
+            while let Some(pos) = pendientes.pop() {
+                if label_of(&pos).starts_with("Trail") {
+                    for next in followpos_map.get(&pos).into_iter().flatten() {
+                        if !closed.contains(next) {
+                            closed.push(next.clone());
+                            pendientes.push(next.clone());
+                        }
                     }
                 }
             }
+            closed.sort();
+            closed.dedup();
+            closed
+        };
+
+        let mut dfa = Dfa::new('A', token_list.clone());
+        let mut names: HashMap<Vec<String>, char> = HashMap::new();
+        let mut pending: VecDeque<Vec<String>> = VecDeque::new();
+        let mut state_letter = 'A';
+
+        let (bol_positions, plain): (Vec<String>, Vec<String>) = root_firstpos
+            .into_iter()
+            .partition(|pos| label_of(pos).starts_with("Bol"));
+        let start_set = closure(plain.clone());
+        names.insert(start_set.clone(), state_letter);
+        pending.push_back(start_set);
+
+        // Al inicio de línea el ^ ya se cumplió, se arranca también desde sus followpos
+        if !bol_positions.is_empty() {
+            let mut bol_set = plain;
+            for pos in &bol_positions {
+                bol_set.extend(followpos_map.get(pos).cloned().unwrap_or_default());
+            }
+            let bol_set = closure(bol_set);
+            let bol_name = match names.get(&bol_set) {
+                Some(&name) => name,
+                None => {
+                    state_letter = next_state_name(state_letter);
+                    names.insert(bol_set.clone(), state_letter);
+                    pending.push_back(bol_set);
+                    state_letter
+                }
+            };
+            dfa.bol_start = Some(bol_name);
         }
 
-        // println!("Columns: {:?}", columns);
-
-        while !state_queue.is_empty() {
-            // Obtener el primer estado y removerlo de state_queue
-            let (state_key, state_value) = fake_state_queue.drain().next().unwrap();
-            state_queue.remove(&state_key.clone());
-            // println!("Processing state: {} -> {:?}", state_key, state_value);
-
-            // Agregar el estado a visited_states
-            visited_states.insert(state_key.clone(), state_value.clone());
-
-            // Crea los valores de cada columna
-            for column in &columns {
-                let mut column_vector: Vec<String> = Vec::new();
-                // println!("Processing column: {}", column);
-
-                // Verificar que números en state_value están asociados a la columna
-                for number in &state_value {
-                    if let Some(symbol) = labels_map.get(number) {
-                        if let Some(start) = symbol.find('\'') {
-                            if let Some(end) = symbol[start + 1..].find('\'') {
-                                let extracted = &symbol[start + 1..start + 1 + end];
-                                if extracted == column {
-                                    if let Some(followpos_values) = followpos_map.get(number) {
-                                        let mut set: HashSet<_> =
-                                            column_vector.iter().cloned().collect();
-                                        for val in followpos_values {
-                                            if set.insert(val.clone()) {
-                                                // insert() returns false if the value already exists
-                                                column_vector.push(val.to_string());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+        while let Some(set) = pending.pop_front() {
+            let name = names[&set];
+
+            let mut tokens: HashSet<String> = HashSet::new();
+            for pos in &set {
+                let label = label_of(pos);
+                if let Some(owner) = marker_owner(label, "Token('") {
+                    tokens.insert(owner);
+                } else if let Some(owner) = marker_owner(label, "Trail('/") {
+                    dfa.trail.entry(name).or_default().insert(owner);
+                }
+            }
+            // Si varias reglas aceptan, gana la que aparece primero en el spec
+            if let Some(winner) = token_list.iter().find(|t| tokens.contains(*t)) {
+                dfa.accept.insert(name, winner.clone());
+            }
+
+            let mut row = HashMap::new();
+            for &(start, end) in &classes {
+                let mut next: Vec<String> = Vec::new();
+                for pos in &set {
+                    if let Some(&(s, e)) = ranges.get(pos) {
+                        if s <= start && end <= e {
+                            next.extend(followpos_map.get(pos).cloned().unwrap_or_default());
                         }
                     }
                 }
-                // println!("Column vector: {:?}", column_vector);
-
-                // Si el column_vector tiene elementos, guardamos en state_map
-                if !column_vector.is_empty() {
-                    // Verificar si el estado creado ya existe
-                    let column_set: HashSet<_> = column_vector.iter().cloned().collect();
-
-                    // println!("State Queue before adding new state: {:?}", state_queue);
-                    // Verificar si ya existe en visited_states o en state_queue
-                    let assigned_letter = visited_states.iter().chain(state_queue.iter()).find_map(|(key, value)| {
-                        let v_set: HashSet<_> = value.iter().cloned().collect();
-                        if v_set == column_set {
-                            Some(key.clone())
-                        } else {
-                            None
-                        }
-                    }).or_else(|| {
-                        // Si no existe, avanzar la letra y agregarlo a la queue
-                        state_letter = (state_letter as u8 + 1) as char;
-                        state_queue.insert(state_letter.to_string(), column_vector.clone());
-                        // println!("New state added to queue: {}", state_letter);
-                        Some(state_letter.to_string())
-                    });
-                    // println!("State Queue after adding new state: {:?}", state_queue);
-
-                    // Insertar o actualizar el valor en state_map
-                    if let Some(assigned_letter) = assigned_letter {
-                        // Verificar si el estado es de aceptación
-                        if column_vector.iter().any(|num| {
-                            if let Some(symbol) = labels_map.get(num) {
-                                // println!("Checking if symbol '{}' (from {}) starts with 'Sentinel' to accept {}", symbol, num, assigned_letter);
-                                symbol.starts_with("Sentinel")
-                            } else {
-                                // println!("Number {} not found in labels_map", num);
-                                false
-                            }
-                        }) {
-                            acceptance_states.insert(assigned_letter.chars().next().unwrap());
-                            // println!("State {} is acceptance state", state_letter);
-                        }
+                if next.is_empty() {
+                    continue;
+                }
+                let next = closure(next);
+                let dest = match names.get(&next) {
+                    Some(&dest) => dest,
+                    None => {
+                        state_letter = next_state_name(state_letter);
+                        names.insert(next.clone(), state_letter);
+                        pending.push_back(next);
+                        state_letter
+                    }
+                };
+                row.insert(class_key(start, end), dest);
+            }
+            dfa.transitions.insert(name, row);
+        }
 
-                        state_map
-                            .entry(state_key.chars().next().unwrap())
-                            .or_insert_with(HashMap::new)
-                            .insert(
-                                column.replace(',' , "-").to_string(),
-                                assigned_letter.chars().next().unwrap(),
-                            );
+        // println!("Mapa de Estados: {:?}", dfa.transitions);
+        // println!("Estados de aceptación: {:?}", dfa.accept);
 
-                        
-                        // println!("Inserted/Updated in state_map: {} -> {} -> {}", state_key, column, assigned_letter);
-                    }
+        dfa
+    }
+
+}
+
+// Estado compartido del recorrido de read_tree: las etiquetas y un contador por
+// tipo de nodo
+struct Labeler {
+    labels: HashMap<String, String>,
+    literal_count: usize,
+    union_count: usize,
+    kleene_count: usize,
+    concat_count: usize,
+    plus_count: usize,
+    optional_count: usize,
+    token_list: Vec<String>,
+}
+
+impl Default for Labeler {
+    fn default() -> Self {
+        Self {
+            labels: HashMap::new(),
+            literal_count: 1,
+            union_count: 1,
+            kleene_count: 1,
+            concat_count: 1,
+            plus_count: 1,
+            optional_count: 1,
+            token_list: Vec::new(),
+        }
+    }
+}
+
+impl Labeler {
+    // Función recursiva que recorre el árbol y asigna etiquetas
+    fn traverse(&mut self, node: &TreeNode) -> String {
+        // println!("Visitando nodo: {:?}", node.get_value());
+
+        // Obtener los identificadores de los hijos (si existen)
+        let left_id = node.get_left().map(|left| {
+            let id = self.traverse(&left);
+            // println!("Nodo izquierdo: {:?} -> ID: {:?}", left.get_value(), id);
+            id
+        });
+        let right_id = node.get_right().map(|right| {
+            let id = self.traverse(&right);
+            // println!("Nodo izquierdo: {:?} -> ID: {:?}", right.get_value(), id);
+            id
+        });
+
+        // Asignar identificador al nodo actual
+        let node_id = match node.get_value() {
+            Token::Literal(c) => {
+                let id = self.literal_count.to_string();
+                self.labels.insert(id.clone(), format!("Literal('{}')", c));
+                self.literal_count += 1;
+                id
+            }
+            Token::Range(c, d) => {
+                let id = self.literal_count.to_string();
+                self.labels.insert(id.clone(), format!("Range('{},{}')", c, d));
+                self.literal_count += 1;
+                id
+            }
+            Token::Tokener(c,) => {
+                let id = self.literal_count.to_string();
+                self.labels.insert(id.clone(), format!("Token('{}')", c));
+                self.token_list.push(c.to_string());
+                self.literal_count += 1;
+                id
+            }
+            Token::Trail(c) => {
+                // Marcador de contexto posterior, su columna es "/token"
+                let id = self.literal_count.to_string();
+                self.labels.insert(id.clone(), format!("Trail('/{}')", c));
+                self.literal_count += 1;
+                id
+            }
+            Token::Bol => {
+                // Ancla de inicio de línea, su columna es "BOL"
+                let id = self.literal_count.to_string();
+                self.labels.insert(id.clone(), "Bol('BOL')".to_string());
+                self.literal_count += 1;
+                id
+            }
+            Token::Union => {
+                let id = format!("alpha{}", self.union_count);
+                self.union_count += 1;
+                if let (Some(c1), Some(c2)) = (left_id.clone(), right_id.clone()) {
+                    self.labels.insert(id.clone(), format!("({}, {})", c1, c2));
+                }
+                id
+            }
+            Token::Kleene => {
+                let id = format!("beta{}", self.kleene_count);
+                self.kleene_count += 1;
+                if let Some(c1) = left_id.clone() {
+                    self.labels.insert(id.clone(), format!("({})", c1));
+                }
+                id
+            }
+            Token::Plus => {
+                let id = format!("delta{}", self.plus_count);
+                self.plus_count += 1;
+                if let Some(c1) = left_id.clone() {
+                    self.labels.insert(id.clone(), format!("({})", c1));
+                }
+                id
+            }
+            Token::Optional => {
+                let id = format!("theta{}", self.optional_count);
+                self.optional_count += 1;
+                if let Some(c1) = left_id.clone() {
+                    self.labels.insert(id.clone(), format!("({})", c1));
+                }
+                id
+            }
+            Token::Concat => {
+                let id = format!("gama{}", self.concat_count);
+                self.concat_count += 1;
+                if let (Some(c1), Some(c2)) = (left_id.clone(), right_id.clone()) {
+                    self.labels.insert(id.clone(), format!("({}, {})", c1, c2));
                 }
+                id
+            }
+            Token::Sentinel => {
+                let id = self.literal_count.to_string();
+                self.labels.insert(id.clone(), "Sentinel".to_string());
+                self.literal_count += 1;
+                id
             }
-            fake_state_queue = state_queue.clone();
-            // println!("State Queue after processing: {:?}", state_queue);
+            Token::Empty => {
+                let id = "empty".to_string();
+                self.labels.insert(id.clone(), "Empty".to_string());
+                id
+            }
+            _ => unreachable!("Unexpected token type in syntax tree"),
+        };
 
-            // println!("Visited States after processing {}: {:?}", state_key, visited_states);
-            // println!("-------------------------------------------");
-        }
+        // println!("Asignando etiquieta: {} -> {:?}", node_id, labels.get(&node_id));
 
-        // println!("Mapa de Estados: {:?}", state_map);
-        // println!("Estados de aceptación: {:?}", acceptance_states);
+        node_id
+    }
+}
 
-        (state_map, acceptance_states, token_list)
+// "Literal('a')" -> (a, a), "Range('a,z')" -> (a, z)
+fn leaf_range(label: &str) -> Option<(char, char)> {
+    if let Some(inner) = label.strip_prefix("Literal('").and_then(|l| l.strip_suffix("')")) {
+        let mut chars = inner.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Some((c, c)),
+            _ => None,
+        };
+    }
+    let inner = label.strip_prefix("Range('")?.strip_suffix("')")?;
+    let parts: Vec<char> = inner.chars().collect();
+    if parts.len() == 3 && parts[1] == ',' {
+        Some((parts[0], parts[2]))
+    } else {
+        None
     }
+}
 
+// "Token('7')" -> "7", "Trail('/7')" -> "7"
+fn marker_owner(label: &str, prefix: &str) -> Option<String> {
+    label
+        .strip_prefix(prefix)
+        .and_then(|l| l.strip_suffix("')"))
+        .map(|owner| owner.to_string())
 }
 
 fn extract_children(value: &str) -> Option<(String, String)> {
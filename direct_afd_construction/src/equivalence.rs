@@ -0,0 +1,141 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::alphabet::{key_range, split_classes};
+use crate::dfa::Dfa;
+
+/// Entrada más corta en la que dos lexers asignan tokens distintos.
+/// `None` como token significa que esa entrada no es aceptada.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub input: String,
+    pub inicio_de_linea: bool,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+// Clases comunes a los dos DFAs, cada una representada por su primer caracter
+fn common_symbols(a: &Dfa, b: &Dfa) -> Vec<char> {
+    let ranges: Vec<(char, char)> = a
+        .transitions
+        .values()
+        .chain(b.transitions.values())
+        .flat_map(|row| row.keys().map(|key| key_range(key)))
+        .collect();
+    let mut classes = split_classes(&ranges);
+    classes.sort();
+    classes.into_iter().map(|(start, _)| start).collect()
+}
+
+// Estado del autómata producto; None es el estado muerto de ese lado
+type Pair = (Option<char>, Option<char>);
+
+// BFS sobre el autómata producto
+fn bfs(
+    a: &Dfa,
+    b: &Dfa,
+    a_start: char,
+    b_start: char,
+    symbols: &[char],
+) -> Option<(String, Option<String>, Option<String>)> {
+    let start = (Some(a_start), Some(b_start));
+    let mut parent: HashMap<Pair, Option<(Pair, char)>> = HashMap::from([(start, None)]);
+    let mut pendientes = VecDeque::from([start]);
+    while let Some(pair) = pendientes.pop_front() {
+        let left = pair.0.and_then(|s| a.accept.get(&s)).cloned();
+        let right = pair.1.and_then(|s| b.accept.get(&s)).cloned();
+        if left != right {
+            let mut input = Vec::new();
+            let mut current = pair;
+            while let Some((prev, c)) = parent[&current] {
+                input.push(c);
+                current = prev;
+            }
+            return Some((input.into_iter().rev().collect(), left, right));
+        }
+        for &c in symbols {
+            let next = (
+                pair.0.and_then(|s| a.step(s, c)),
+                pair.1.and_then(|s| b.step(s, c)),
+            );
+            if next == (None, None) || parent.contains_key(&next) {
+                continue;
+            }
+            parent.insert(next, Some((pair, c)));
+            pendientes.push_back(next);
+        }
+    }
+    None
+}
+
+/// Compara dos DFAs por el token que asignan a cada entrada, también al inicio
+/// de línea. Devuelve None si son equivalentes o la entrada más corta en la que
+/// difieren.
+pub fn find_difference(a: &Dfa, b: &Dfa) -> Option<Counterexample> {
+    let symbols = common_symbols(a, b);
+    let mut best: Option<Counterexample> = None;
+    for inicio_de_linea in [false, true] {
+        let (a_start, b_start) = if inicio_de_linea {
+            (
+                a.bol_start.unwrap_or(a.start),
+                b.bol_start.unwrap_or(b.start),
+            )
+        } else {
+            (a.start, b.start)
+        };
+        if let Some((input, left, right)) = bfs(a, b, a_start, b_start, &symbols) {
+            let shorter = best
+                .as_ref()
+                .is_none_or(|found| input.chars().count() < found.input.chars().count());
+            if shorter {
+                best = Some(Counterexample {
+                    input,
+                    inicio_de_linea,
+                    left,
+                    right,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Cadena aceptada más corta: la diferencia contra un DFA que no acepta nada.
+pub fn shortest_match(dfa: &Dfa) -> Option<Counterexample> {
+    find_difference(dfa, &Dfa::new('A', Vec::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+
+    fn lexer(rules: &[(usize, &str)]) -> Dfa {
+        let mut builder = LexerBuilder::new();
+        for (index, pattern) in rules {
+            builder.add_rule(*index, parse_rule(pattern));
+        }
+        minimize_dfa(&builder.build())
+    }
+
+    #[test]
+    fn test_equivalent_and_shortest_difference() {
+        let plus = lexer(&[(1, "[a-z]+")]);
+        assert_eq!(find_difference(&plus, &lexer(&[(1, "[a-z][a-z]*")])), None);
+
+        let diff = find_difference(&plus, &lexer(&[(1, "[a-y]+")])).unwrap();
+        assert_eq!(diff.input, "z");
+        assert_eq!((diff.left, diff.right), (Some("1".to_string()), None));
+
+        // Con la prioridad invertida la palabra reservada pasa a ser identificador
+        let keyword_first = lexer(&[(1, "if"), (2, "[a-z]+")]);
+        let ident_first = lexer(&[(2, "[a-z]+"), (1, "if")]);
+        let diff = find_difference(&keyword_first, &ident_first).unwrap();
+        assert_eq!(diff.input, "if");
+        assert_eq!(
+            (diff.left, diff.right),
+            (Some("1".to_string()), Some("2".to_string()))
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::alphabet::key_range;
+use crate::dfa::Dfa;
+use crate::grammar_tree::TreeNode;
+use crate::inf_to_pos::Token;
+use crate::lexer_builder::LexerBuilder;
+use crate::minimize::minimize_dfa;
+
+// `&` y `~` no tienen posiciones en el método directo ni fragmentos en Thompson.
+// Cada subárbol con estos operadores se compila a un DFA (producto de los DFAs
+// de sus hijos) y se vuelve a escribir como regex normal por eliminación de
+// estados, así todos los métodos de construcción reciben un árbol sin ellos.
+
+fn node(value: Token, left: Option<TreeNode>, right: Option<TreeNode>) -> TreeNode {
+    TreeNode::new(value, left.map(Rc::new), right.map(Rc::new))
+}
+
+pub fn has_extended(tree: &TreeNode) -> bool {
+    matches!(tree.get_value(), Token::And | Token::Not)
+        || tree.get_left().is_some_and(|l| has_extended(&l))
+        || tree.get_right().is_some_and(|r| has_extended(&r))
+}
+
+/// Copia del árbol con cada `&` y `~` reemplazado por una regex equivalente.
+pub fn lower(tree: &TreeNode) -> TreeNode {
+    lower_or_empty(tree).expect("Expression with & or ~ matches no string")
+}
+
+// None si el subárbol no acepta ninguna cadena, como a&b; se simplifica hacia
+// arriba hasta que la regla completa acepte algo
+fn lower_or_empty(tree: &TreeNode) -> Option<TreeNode> {
+    if matches!(tree.get_value(), Token::And | Token::Not) {
+        return dfa_to_tree(&fragment_dfa(tree));
+    }
+    let left = tree.get_left().map(|l| lower_or_empty(&l));
+    let right = tree.get_right().map(|r| lower_or_empty(&r));
+    match (tree.get_value(), left, right) {
+        (Token::Union, Some(None), Some(other)) | (Token::Union, Some(other), Some(None)) => other,
+        (Token::Kleene | Token::Optional, Some(None), _) => Some(node(Token::Empty, None, None)),
+        (_, Some(None), _) | (_, _, Some(None)) => None,
+        (value, left, right) => Some(node(value.clone(), left.flatten(), right.flatten())),
+    }
+}
+
+fn fragment_dfa(tree: &TreeNode) -> Dfa {
+    match tree.get_value() {
+        Token::And => {
+            let left = fragment_dfa(&tree.get_left().unwrap());
+            left.intersection(&fragment_dfa(&tree.get_right().unwrap()))
+        }
+        Token::Not => fragment_dfa(&tree.get_left().unwrap()).complement("0"),
+        _ => {
+            let Some(plain) = lower_or_empty(tree) else {
+                return Dfa::new('A', Vec::new());
+            };
+            check_plain(&plain);
+            // Un fragmento como _* puede aceptar ε, eso lo decide la regla completa
+            let mut builder = LexerBuilder::new();
+            builder.allow_empty(true);
+            builder.add_rule(0, plain);
+            minimize_dfa(&builder.build())
+        }
+    }
+}
+
+fn check_plain(tree: &TreeNode) {
+    match tree.get_value() {
+        Token::Trail(_) => panic!("Trailing context '/' is not allowed inside & or ~"),
+        Token::Bol => panic!("'^' is not allowed inside & or ~"),
+        _ => {}
+    }
+    if let Some(left) = tree.get_left() {
+        check_plain(&left);
+    }
+    if let Some(right) = tree.get_right() {
+        check_plain(&right);
+    }
+}
+
+// Etiquetas de la eliminación de estados; None es ε
+type Label = Option<TreeNode>;
+
+fn concat(a: Label, b: Label) -> Label {
+    match (a, b) {
+        (None, b) => b,
+        (a, None) => a,
+        (Some(a), Some(b)) => Some(node(Token::Concat, Some(a), Some(b))),
+    }
+}
+
+fn union(a: Label, b: Label) -> Label {
+    match (a, b) {
+        (None, None) => None,
+        (None, Some(x)) | (Some(x), None) => match x.get_value() {
+            Token::Kleene | Token::Optional => Some(x),
+            _ => Some(node(Token::Optional, Some(x), None)),
+        },
+        (Some(a), Some(b)) => Some(node(Token::Union, Some(a), Some(b))),
+    }
+}
+
+fn star(a: Label) -> Label {
+    a.map(|x| match x.get_value() {
+        Token::Kleene => x,
+        _ => node(Token::Kleene, Some(x), None),
+    })
+}
+
+/// Regex equivalente a un DFA por eliminación de estados, None si no acepta nada.
+fn dfa_to_tree(dfa: &Dfa) -> Option<TreeNode> {
+    let mut states: Vec<char> = dfa.transitions.keys().cloned().collect();
+    states.sort();
+    let index: HashMap<char, usize> = states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let (start, end) = (states.len(), states.len() + 1);
+
+    let mut edges: HashMap<(usize, usize), Label> = HashMap::new();
+    let add = |edges: &mut HashMap<(usize, usize), Label>, from, to, label: Label| {
+        let merged = match edges.remove(&(from, to)) {
+            Some(existing) => union(existing, label),
+            None => label,
+        };
+        edges.insert((from, to), merged);
+    };
+    add(&mut edges, start, index[&dfa.start], None);
+    for (state, row) in &dfa.transitions {
+        let mut keys: Vec<&String> = row.keys().collect();
+        keys.sort();
+        for key in keys {
+            let (lo, hi) = key_range(key);
+            let leaf = if lo == hi { Token::Literal(lo) } else { Token::Range(lo, hi) };
+            add(&mut edges, index[state], index[&row[key]], Some(node(leaf, None, None)));
+        }
+        if dfa.accept.contains_key(state) {
+            add(&mut edges, index[state], end, None);
+        }
+    }
+
+    for k in 0..states.len() {
+        let self_loop = star(edges.remove(&(k, k)).flatten());
+        let incoming: Vec<(usize, Label)> = edges
+            .iter()
+            .filter(|((_, to), _)| *to == k)
+            .map(|((from, _), label)| (*from, label.clone()))
+            .collect();
+        let outgoing: Vec<(usize, Label)> = edges
+            .iter()
+            .filter(|((from, _), _)| *from == k)
+            .map(|((_, to), label)| (*to, label.clone()))
+            .collect();
+        edges.retain(|(from, to), _| *from != k && *to != k);
+        for (from, in_label) in &incoming {
+            for (to, out_label) in &outgoing {
+                let path = concat(concat(in_label.clone(), self_loop.clone()), out_label.clone());
+                add(&mut edges, *from, *to, path);
+            }
+        }
+    }
+
+    match edges.remove(&(start, end)) {
+        Some(Some(tree)) => Some(tree),
+        Some(None) => Some(node(Token::Empty, None, None)),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer_builder::{Construction, LexerBuilder};
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+    use crate::token_identifier::asignar_token;
+
+    #[test]
+    fn test_block_comment_with_complement() {
+        // "/*" ~(_* "*/" _*) "*/"
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule(r"\/\*~(_*\*\/_*)\*\/"));
+        builder.add_rule(2, parse_rule("[a-z]+&~(if|while)"));
+        let dfa = minimize_dfa(&builder.build());
+        assert_eq!(asignar_token(&dfa, "/* a * / b */", false), "1");
+        assert_eq!(asignar_token(&dfa, "/**/", false), "1");
+        assert_eq!(asignar_token(&dfa, "/* a */ b */", false), "UNKNOWN");
+        assert_eq!(asignar_token(&dfa, "iff", false), "2");
+        assert_eq!(asignar_token(&dfa, "while", false), "UNKNOWN");
+
+        // Las derivadas soportan & y ~ sin pasar por el producto
+        builder.construction(Construction::Derivative);
+        let derived = minimize_dfa(&builder.build());
+        assert!(crate::dfa::isomorphic(&dfa, &derived));
+    }
+
+    #[test]
+    fn test_empty_fragments_simplify_away() {
+        // a&b no acepta nada: en una unión sobra y bajo ? o * queda ε
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule("(a&b)|c"));
+        builder.add_rule(2, parse_rule("x(~(_*))?"));
+        builder.add_rule(3, parse_rule("y(a&b)*z"));
+        let derived = minimize_dfa(&builder.construction(Construction::Derivative).build());
+        for construction in [Construction::Direct, Construction::Thompson] {
+            let dfa = minimize_dfa(&builder.construction(construction).build());
+            assert_eq!(asignar_token(&dfa, "c", false), "1");
+            assert_eq!(asignar_token(&dfa, "a", false), "UNKNOWN");
+            assert_eq!(asignar_token(&dfa, "x", false), "2");
+            assert_eq!(asignar_token(&dfa, "yz", false), "3");
+            assert!(crate::dfa::isomorphic(&dfa, &derived));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Expression with & or ~ matches no string")]
+    fn test_rule_with_empty_language_is_rejected() {
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule("a(b&c)"));
+        builder.build();
+    }
+}
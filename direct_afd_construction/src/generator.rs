@@ -0,0 +1,253 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::alphabet::key_range;
+use crate::dfa::Dfa;
+use crate::token_identifier::fin_de_lexema;
+
+// Generador de entradas válidas para probar parsers: camina el DFA al azar hacia
+// un estado donde gana el token pedido. Con la misma semilla sale lo mismo.
+
+// Largo a partir del cual el camino va directo a aceptar
+const MAX_LEN: usize = 16;
+
+/// PRNG splitmix64, suficiente para fuzzing y sin dependencias.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Número en 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+pub struct Generator {
+    dfa: Dfa,
+    rng: Rng,
+    // token -> estado -> pasos mínimos hasta un estado donde gana el token
+    distance: HashMap<String, HashMap<char, usize>>,
+}
+
+impl Generator {
+    pub fn new(dfa: &Dfa, seed: u64) -> Self {
+        let mut distance: HashMap<String, HashMap<char, usize>> = HashMap::new();
+        for token in dfa.accept.values() {
+            if !distance.contains_key(token) {
+                distance.insert(token.clone(), distances_to(dfa, token));
+            }
+        }
+        Self {
+            dfa: dfa.clone(),
+            rng: Rng::new(seed),
+            distance,
+        }
+    }
+
+    fn start(&self, inicio_de_linea: bool) -> char {
+        match self.dfa.bol_start {
+            Some(bol) if inicio_de_linea => bol,
+            _ => self.dfa.start,
+        }
+    }
+
+    /// Tokens que se pueden generar desde el inicio, en orden de prioridad
+    pub fn tokens(&self, inicio_de_linea: bool) -> Vec<String> {
+        let start = self.start(inicio_de_linea);
+        self.dfa
+            .token_list
+            .iter()
+            .filter(|t| {
+                self.distance
+                    .get(*t)
+                    .is_some_and(|d| d.contains_key(&start))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn random_char(&mut self, key: &str) -> char {
+        let (start, end) = key_range(key);
+        // La mitad de las veces un caracter visible si la clase tiene alguno
+        let (lo, hi) = if start <= '~' && end >= ' ' && self.rng.below(2) == 0 {
+            (start.max(' ') as u32, end.min('~') as u32)
+        } else {
+            (start as u32, end as u32)
+        };
+        loop {
+            let code = lo + self.rng.below((hi - lo + 1) as usize) as u32;
+            if let Some(c) = char::from_u32(code) {
+                return c;
+            }
+        }
+    }
+
+    /// Lexema al azar al que el scanner le asigna `token`. Con contexto
+    /// posterior solo devuelve la parte r.
+    pub fn lexeme(&mut self, token: &str, inicio_de_linea: bool) -> Option<String> {
+        let distance = self.distance.get(token)?.clone();
+        let mut state = self.start(inicio_de_linea);
+        distance.get(&state)?;
+        let mut text = String::new();
+        let mut len = 0;
+        loop {
+            let accepts = self.dfa.accept.get(&state).is_some_and(|t| t == token);
+            let mut options: Vec<(String, char)> = self.dfa.transitions[&state]
+                .iter()
+                .filter(|(_, dest)| match distance.get(dest) {
+                    // Pasado el largo máximo solo se avanza hacia la aceptación
+                    Some(d) => len < MAX_LEN || *d < distance[&state],
+                    None => false,
+                })
+                .map(|(key, &dest)| (key.clone(), dest))
+                .collect();
+            if accepts && (options.is_empty() || len >= MAX_LEN || self.rng.below(3) == 0) {
+                break;
+            }
+            options.sort();
+            let (key, dest) = options[self.rng.below(options.len())].clone();
+            text.push(self.random_char(&key));
+            state = dest;
+            len += 1;
+        }
+        if let Some(corte) = fin_de_lexema(&self.dfa, &text, token, inicio_de_linea) {
+            text = text.chars().take(corte).collect();
+        }
+        Some(text)
+    }
+
+    /// `count` tokens al azar con sus lexemas, para unirlos con `separator`.
+    /// Las reglas con ^ solo salen al inicio o si el separador termina en '\n'.
+    /// Las reglas r/s no salen: sin su s el scanner le da la parte r a otra
+    /// regla, y con s la entrada tendría tokens que no están en la secuencia.
+    pub fn sequence(&mut self, count: usize, separator: &str) -> Vec<(String, String)> {
+        let mut sequence = Vec::new();
+        for i in 0..count {
+            let inicio_de_linea = i == 0 || separator.ends_with('\n');
+            let tokens: Vec<String> = self
+                .tokens(inicio_de_linea)
+                .into_iter()
+                .filter(|token| !self.dfa.trail.values().any(|tokens| tokens.contains(token)))
+                .collect();
+            if tokens.is_empty() {
+                break;
+            }
+            let token = tokens[self.rng.below(tokens.len())].clone();
+            let lexeme = self.lexeme(&token, inicio_de_linea).unwrap();
+            sequence.push((token, lexeme));
+        }
+        sequence
+    }
+}
+
+// BFS hacia atrás desde los estados donde gana el token
+fn distances_to(dfa: &Dfa, token: &str) -> HashMap<char, usize> {
+    let mut reverse: HashMap<char, Vec<char>> = HashMap::new();
+    for (&from, row) in &dfa.transitions {
+        for &to in row.values() {
+            reverse.entry(to).or_default().push(from);
+        }
+    }
+    let mut distance: HashMap<char, usize> = HashMap::new();
+    let mut pendientes = VecDeque::new();
+    for (&state, winner) in &dfa.accept {
+        if winner == token {
+            distance.insert(state, 0);
+            pendientes.push_back(state);
+        }
+    }
+    while let Some(state) = pendientes.pop_front() {
+        for &prev in reverse.get(&state).into_iter().flatten() {
+            if !distance.contains_key(&prev) {
+                distance.insert(prev, distance[&state] + 1);
+                pendientes.push_back(prev);
+            }
+        }
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+    use crate::simulate;
+    use crate::token_identifier::asignar_token;
+
+    #[test]
+    fn test_generated_lexemes_get_their_token() {
+        let mut builder = LexerBuilder::new();
+        for (index, pattern) in [
+            "while",
+            "[a-z]+",
+            "-?[0-9]+(\\.[0-9]*)?",
+            "[0-9]+/\\.\\.",
+            "^#[a-z]+",
+        ]
+        .iter()
+        .enumerate()
+        {
+            builder.add_rule(index, parse_rule(pattern));
+        }
+        let dfa = minimize_dfa(&builder.build());
+
+        let mut generator = Generator::new(&dfa, 42);
+        assert_eq!(generator.tokens(false), vec!["0", "1", "2", "3"]);
+        for _ in 0..200 {
+            for token in generator.tokens(true) {
+                let lexeme = generator.lexeme(&token, true).unwrap();
+                // La parte r de r/s sin su s la toma otra regla
+                if token != "3" {
+                    assert_eq!(asignar_token(&dfa, &lexeme, true), token, "{:?}", lexeme);
+                }
+            }
+        }
+
+        // La parte r de r/s sola no es el token 3, así que no sale en secuencias
+        for seed in 0..50 {
+            let sequence = Generator::new(&dfa, seed).sequence(20, "\n");
+            assert!(sequence.iter().all(|(token, _)| token != "3"));
+        }
+
+        // Misma semilla, misma secuencia
+        let first = Generator::new(&dfa, 7).sequence(20, " ");
+        assert_eq!(first, Generator::new(&dfa, 7).sequence(20, " "));
+        assert_ne!(first, Generator::new(&dfa, 8).sequence(20, " "));
+    }
+
+    #[test]
+    fn test_sequences_scan_back_to_their_tokens() {
+        // Solo ASCII para que simulate pueda leer la entrada
+        let mut builder = LexerBuilder::new();
+        for (index, pattern) in ["while", "[a-z]+", "[0-9]+/\\.\\.", "[0-9]+", "\\.\\.", " +"]
+            .iter()
+            .enumerate()
+        {
+            builder.add_rule(index, parse_rule(pattern));
+        }
+        let dfa = minimize_dfa(&builder.build());
+        for seed in 0..50 {
+            let sequence = Generator::new(&dfa, seed).sequence(10, " ");
+            let text: Vec<&str> = sequence.iter().map(|(_, lexeme)| lexeme.as_str()).collect();
+            // Sin los espacios, que son la regla 5
+            let expected: Vec<&String> = sequence.iter().map(|(token, _)| token).filter(|t| *t != "5").collect();
+            let scanned = simulate(text.join(" "), dfa.clone(), None);
+            let scanned: Vec<&String> = scanned.iter().filter(|t| *t != "5").collect();
+            assert_eq!(scanned, expected, "{:?}", text);
+        }
+    }
+}
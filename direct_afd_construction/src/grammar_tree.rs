@@ -14,6 +14,10 @@ pub struct TreeNode{
     right: Option<Rc<TreeNode>>
 }
 impl TreeNode{
+    pub fn new(value: Token, left: Option<Rc<TreeNode>>, right: Option<Rc<TreeNode>>) -> Self {
+        Self { value, left, right }
+    }
+
     pub fn print_tree(self, level: usize, prefix: &str)->String{
         let space = " ".repeat(level*4);
         let mut ret = format!("{}{}{:?}\n",space,prefix,self.value);
@@ -47,6 +51,17 @@ impl TreeNode{
     pub fn get_value(&self) -> &Token {
         &self.value
     }
+
+    /// Copia del subárbol con los marcadores de r/s sin dueño asignados al token `id`.
+    pub fn with_trail_owner(&self, id: &str) -> TreeNode {
+        let value = match &self.value {
+            Token::Trail(owner) if owner.is_empty() => Token::Trail(id.to_string()),
+            other => other.clone(),
+        };
+        let left = self.left.as_ref().map(|l| Rc::new(l.with_trail_owner(id)));
+        let right = self.right.as_ref().map(|r| Rc::new(r.with_trail_owner(id)));
+        TreeNode::new(value, left, right)
+    }
 }
 
 impl Tree{
@@ -54,6 +69,10 @@ impl Tree{
         Self { nodes: Vec::new(), root: None }
     }
 
+    pub fn from_root(root: TreeNode)->Self{
+        Self { nodes: Vec::new(), root: Some(Rc::new(root)) }
+    }
+
     pub fn generate(&mut self, tokens: Vec<Token>)->Rc<TreeNode>{
         let mut stack : Vec<TreeNode> = Vec::new();
 
@@ -77,11 +96,35 @@ impl Tree{
                             };
                             stack.push(operator);
                         }
-                        _=>{}
+                        _=>panic!("Invalid postfix expression. {:?} needs two operands", tk)
                     }
 
                 },
-                Token::Kleene=>{
+                Token::Trail(_)=>{
+                    // r/s se guarda como (r ∘ marcador) ∘ s, el marcador es una posición más
+                    match (stack.pop(), stack.pop()){
+                        (Some(second), Some(first))=>{
+                            let marker = TreeNode{
+                                value: tk,
+                                left: None,
+                                right: None
+                            };
+                            let head = TreeNode{
+                                value: Token::Concat,
+                                left: Some(Rc::new(first)),
+                                right: Some(Rc::new(marker))
+                            };
+                            let operator = TreeNode{
+                                value: Token::Concat,
+                                left: Some(Rc::new(head)),
+                                right: Some(Rc::new(second))
+                            };
+                            stack.push(operator);
+                        }
+                        _=>panic!("Invalid postfix expression. / needs two operands")
+                    }
+                },
+                Token::Kleene | Token::Plus | Token::Optional=>{
                     match stack.pop(){
                         Some(first)=>{
                             let operator = TreeNode{
@@ -91,10 +134,10 @@ impl Tree{
                             };
                             stack.push(operator);
                         }
-                        _=>{}
+                        _=>panic!("Invalid postfix expression. {:?} needs an operand", tk)
                     }
                 },
-                Token::Sentinel | Token::Empty=>{
+                Token::Sentinel | Token::Empty | Token::Bol=>{
                     let newnode = TreeNode{
                         value: tk, 
                         left: None,
@@ -114,6 +157,9 @@ impl Tree{
             }
             
         }
+        if stack.len() != 1 {
+            panic!("Invalid postfix expression. {} operands left without operator", stack.len());
+        }
         let root_node = Some(Rc::new(stack[0].clone()));
         self.root = Some(Rc::new(stack[0].clone()));
         root_node.unwrap()
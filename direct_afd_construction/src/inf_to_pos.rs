@@ -14,6 +14,10 @@ pub enum Token {
     Empty,             // % 
     Optional,          // ?
     Tokener(String),   // El token que le pertenece a una variable
+    Trail(String),     // / contexto posterior, guarda el token de su regla
+    Bol,               // ^ inicio de línea
+    And,               // & intersección, solo en el parser
+    Not,               // ~ complemento, solo en el parser
 }
 fn check_range(start: char, end: char)->bool{
     if start>end{
@@ -48,6 +52,11 @@ fn tokenize(input: &str) -> Vec<Token> {
             },
             
             '?' => tokens.push(Token::Optional),
+            '/' => tokens.push(Token::Trail(String::new())),
+            // ^ solo ancla al inicio de una regla o alternativa, si no es literal
+            '^' if matches!(tokens.last(), None | Some(Token::LParen) | Some(Token::Union)) => {
+                tokens.push(Token::Bol)
+            }
             '#' => tokens.push(Token::Sentinel),
             '*' => tokens.push(Token::Kleene),
             '|' => tokens.push(Token::Union),
@@ -77,6 +86,16 @@ fn tokenize(input: &str) -> Vec<Token> {
                         if id.is_empty() {
                             panic!("Invalid Tokener syntax. Expected a String between keys");
                         }
+                        // Los / de la regla quedan ligados a su token
+                        for tk in tokens.iter_mut().rev() {
+                            match tk {
+                                Token::Tokener(_) => break,
+                                Token::Trail(owner) if owner.is_empty() => {
+                                    *owner = id.clone();
+                                }
+                                _ => {}
+                            }
+                        }
                         tokens.push(Token::Tokener(id.clone()));
                         break;
                     } else if c.is_alphanumeric() || c == '_' { 
@@ -101,11 +120,11 @@ fn implicit_concat(prev: &Token, next: &Token) -> bool {
         (prev, next),
         (
             // Char y Char -> concat
-            Token::Literal(_) | Token::Range(_, _) | Token::Sentinel | Token::Empty | Token::Tokener(_),
+            Token::Literal(_) | Token::Range(_, _) | Token::Sentinel | Token::Empty | Token::Tokener(_) | Token::Bol,
             Token::Literal(_) | Token::Range(_, _) | Token::Sentinel | Token::Empty | Token::Tokener(_)
         ) | (
             // Char y ( -> concat
-            Token::Literal(_) | Token::Range(_, _) | Token::Empty | Token::Tokener(_), 
+            Token::Literal(_) | Token::Range(_, _) | Token::Empty | Token::Tokener(_) | Token::Bol, 
             Token::LParen
         ) | (
             // ) y Char -> concat
@@ -113,11 +132,11 @@ fn implicit_concat(prev: &Token, next: &Token) -> bool {
             Token::Literal(_) | Token::Range(_, _) | Token::Sentinel | Token::Empty | Token::Tokener(_)
         ) | (
             // * y Char 
-            Token::Kleene | Token::Plus,
+            Token::Kleene | Token::Plus | Token::Optional,
             Token::Literal(_) | Token::Range(_, _) | Token::Sentinel | Token::Empty | Token::Tokener(_)
         ) | (
             // * y (
-            Token::Kleene | Token::Plus, 
+            Token::Kleene | Token::Plus | Token::Optional, 
             Token::LParen
         ) | (
             // ) y (
@@ -128,19 +147,19 @@ fn implicit_concat(prev: &Token, next: &Token) -> bool {
 }
 fn precedence(token: &Token) -> usize {
     let prec = match token {
-        Token::Kleene => 3,
-        Token::Plus => 3,
-        Token::Concat => 2,
-        Token::Union => 1,
+        Token::Kleene => 4,
+        Token::Plus => 4,
+        Token::Optional => 4,
+        Token::Concat => 3,
+        Token::Union => 2,
+        Token::Trail(_) => 1,
         _ => 0,
     };
     prec
 }
 fn expand(tokens: &Vec<Token>)->Vec<Token>{
     let mut queue: VecDeque<Token> = VecDeque::new();
-    // Replace ? and +
-    // a? -> a|empty 
-    // a+ -> aa*
+    // ? y + se quedan como operadores, el árbol tiene nodos propios para ellos
     for tk in tokens{
         match tk{
             Token::Literal(_c)|Token::Range(_c,_)=>{
@@ -149,59 +168,14 @@ fn expand(tokens: &Vec<Token>)->Vec<Token>{
             Token::Tokener(ref s) => {
                 queue.push_back(tk.clone());
             },
-            Token::Sentinel | Token::RParen | Token::LParen=>{
+            Token::Sentinel | Token::RParen | Token::LParen | Token::Bol=>{
                 queue.push_back(tk.clone());
             }
-            Token::Union | Token::Kleene=>{
+            Token::Union | Token::Kleene | Token::Trail(_)=>{
                 queue.push_back(tk.clone());
             }
-            Token::Optional=>{
-                let last = queue.pop_back().unwrap();
-                if last==Token::RParen || last==Token::Kleene{
-                    queue.push_back(last);
-                    queue.push_back(Token::Union);
-                    queue.push_back(Token::Empty);
-                } else{
-                    queue.push_back(Token::LParen);
-                    queue.push_back(last);
-                    queue.push_back(Token::Union);
-                    queue.push_back(Token::Empty);
-                    queue.push_back(Token::RParen);
-                }
-            }
-            Token::Plus=>{
-                let last = queue.pop_back().unwrap();
-                if last==Token::RParen{
-                    let mut tem_stack: Vec<Token> = Vec::new();
-                    tem_stack.push(last.clone());
-                    let mut tem_que: VecDeque<Token> = VecDeque::new();
-                    for rept_tk in queue.iter().rev(){
-                            if rept_tk==&Token::RParen{
-                                tem_que.push_front(rept_tk.clone());
-                                tem_stack.push(rept_tk.clone());
-                            } else if rept_tk==&Token::LParen{
-                                tem_stack.pop();
-                                tem_que.push_front(rept_tk.clone());
-                                if tem_stack.is_empty(){
-                                    break;
-                                }
-                            } else{
-                                tem_que.push_front(rept_tk.clone());
-                            }
-                    }
-                    tem_que.push_back(Token::RParen);
-                    queue.push_back(last.clone());
-                    for new in tem_que{
-                        queue.push_back(new.clone());
-                    }
-                    queue.push_back(Token::Kleene);
-                } else if last==Token::Kleene{
-                    // Change nothing: a*+ = (a*)(a*)* = a*
-                }else{
-                    queue.push_back(last.clone());
-                    queue.push_back(last.clone());
-                    queue.push_back(Token::Kleene);
-                }
+            Token::Optional | Token::Plus=>{
+                queue.push_back(tk.clone());
             }
             _=>{
                 // TODO exception
@@ -237,9 +211,12 @@ fn shunting_yard(tokens: Vec<Token>)->VecDeque<Token>{
             Token::Tokener(ref s) => {
                 queue.push_back(tk);
             },
-            Token::LParen | Token::Empty=>{
+            Token::LParen=>{
                 stack.push(tk);
             }
+            Token::Empty=>{
+                queue.push_back(tk);
+            }
             Token::RParen =>{
                 while let Some(last) = stack.last().cloned(){
                     if last!=Token::LParen{
@@ -251,11 +228,14 @@ fn shunting_yard(tokens: Vec<Token>)->VecDeque<Token>{
                     }
                 }
             },
-            Token::Sentinel=>{
+            Token::Sentinel | Token::Bol=>{
                 queue.push_back(tk);
             }
+            Token::And | Token::Not=>{
+                panic!("& and ~ are only supported by regex_parser");
+            }
 
-            Token::Kleene | Token::Concat | Token::Plus | Token::Union =>{
+            Token::Kleene | Token::Concat | Token::Plus | Token::Optional | Token::Union | Token::Trail(_) =>{
                 while let Some(last) = stack.last().cloned(){
                     if precedence(&last)>precedence(&tk){   
                         queue.push_back(last);
@@ -266,7 +246,6 @@ fn shunting_yard(tokens: Vec<Token>)->VecDeque<Token>{
                 }
                 stack.push(tk);
             },
-            _=> {}
         }
     }
     while !stack.is_empty(){
@@ -304,5 +283,15 @@ mod tests {
         ];
         let e1 = inf_to_pos(i1);
         assert_eq!(e1,t1);
+
+        // + y ? quedan como operadores postfijos, sin copiar el grupo
+        let i2 = r"(ab)+c?";
+        let t2 = [
+            Token::Literal('a'), Token::Literal('b'), Token::Concat, Token::Plus,
+            Token::Literal('c'), Token::Optional, Token::Concat,
+            Token::Sentinel, Token::Concat
+        ];
+        let e2 = inf_to_pos(i2);
+        assert_eq!(e2,t2);
     }
 }
\ No newline at end of file
@@ -17,7 +17,8 @@ fn clean_reg(reg: &str)->String{
     new_reg
 }
 
-fn split_line(line: &str)->(String, String){
+// (patrón, acción, el patrón tenía comillas)
+fn split_line(line: &str)->(String, String, bool){
    
     // check
     let mut clean = false;
@@ -53,13 +54,15 @@ fn split_line(line: &str)->(String, String){
 
     let mut in_string = false;
     let mut in_action = false;
+    let mut quoted = false;
     while let Some(c) = line_chars.peek(){
         if *c=='\"' && last!='\\'{
             in_string = !in_string;
+            quoted |= !in_action;
 
-        } else if *c=='{'&& last!='\\'{
+        } else if *c=='{'&& last!='\\' && !in_string{
             in_action=true;
-        } else if *c=='}'&& last!='\\'{
+        } else if *c=='}'&& last!='\\' && !in_string{
             in_action=false;
         }
         else{
@@ -67,6 +70,10 @@ fn split_line(line: &str)->(String, String){
                 if in_action{
                     action+=&c.to_string();
                 } else{
+                    // Entre comillas los operadores son literales
+                    if in_string && last!='\\' && "()[]{}*+?|#/&~_^".contains(*c){
+                        argument.push('\\');
+                    }
                     argument+=&c.to_string();
 
                 }
@@ -80,11 +87,14 @@ fn split_line(line: &str)->(String, String){
     }
     // println!("Argument: {}",argument);
     // println!("Action: {}",action);
-    (argument, action)
+    (argument, action, quoted)
 }
-fn get_tk_act(line: &str, line_num: u8)->(String,String,u8){
+// (patrón, acción, línea, es la regla eof); eof solo cuenta sin comillas, "eof"
+// es un patrón normal
+fn get_tk_act(line: &str, line_num: usize)->(String,String,usize,bool){
     let mut splitted= split_line(line);
     let act = splitted.1;
+    let is_eof = !splitted.2 && splitted.0 == "eof";
     let reg = clean_reg(&splitted.0);
     // if splitted.len()==4{ // ideal = [reg, {, action ,} ]
     //     splitted.retain(|word| *word!="{" && *word!="}");
@@ -99,19 +109,27 @@ fn get_tk_act(line: &str, line_num: u8)->(String,String,u8){
     //     // TODO raise exception
     // }
     // (reg, act)
-    (reg,act,line_num)
+    (reg,act,line_num,is_eof)
 }
-pub fn get_line_array(filename:&str)->Vec<(String,String, u8)>{
+pub fn get_line_array(filename:&str)->Vec<(String,String, usize, bool)>{
     let mut definitions: Vec<String> = Vec::new();
-    let mut actions: Vec<(String,String, u8)> = Vec::new();
+    let mut actions: Vec<(String,String, usize, bool)> = Vec::new();
     let mut def_started = false;
     let mut act_started = false;
+    let mut in_comment = false;
     let mut line_num = 0;
     if let Ok(lines) = read_lines(filename) {
         for line in lines {
             if let Ok(content) = line {
-                
-                if content == "{"{
+                // Línea en el archivo, desde 1; es el id de token de la regla
+                line_num+=1;
+
+                // Los comentarios (* ... *) y las líneas vacías no son reglas,
+                // pero cuentan para el número de línea
+                if in_comment || content.trim_start().starts_with("(*"){
+                    in_comment = !content.trim_end().ends_with("*)");
+                } else if content.trim().is_empty(){
+                } else if content == "{"{
                     if !def_started && !act_started{ // Start definition stage
                         def_started = true;
                     }     
@@ -131,9 +149,92 @@ pub fn get_line_array(filename:&str)->Vec<(String,String, u8)>{
                         actions.push(entry);
                     }
                 }
-                line_num+=1;
             }
         }
     }
     actions
-}
\ No newline at end of file
+}
+
+// Líneas de los bloques (* test ... *) con su número de línea, desde 1
+pub fn get_inline_tests(filename:&str)->Vec<(String, usize)>{
+    let mut cases: Vec<(String, usize)> = Vec::new();
+    let mut in_tests = false;
+    if let Ok(lines) = read_lines(filename) {
+        for (line_num, line) in lines.enumerate() {
+            if let Ok(content) = line {
+                if !in_tests && content.trim_start().starts_with("(* test"){
+                    in_tests = true;
+                } else if in_tests && content.trim() == "*)"{
+                    in_tests = false;
+                } else if in_tests && !content.trim().is_empty(){
+                    cases.push((content.trim().to_string(), line_num + 1));
+                }
+            }
+        }
+    }
+    cases
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::regex_parser::parse_rule;
+    use crate::token_identifier::asignar_token;
+
+    fn pattern(line: &str) -> String {
+        get_tk_act(line, 1).0
+    }
+
+    fn matches(reg: &str, input: &str) -> bool {
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule(reg));
+        asignar_token(&builder.build(), input, false) == "1"
+    }
+
+    #[test]
+    fn test_quoted_operators_are_literal() {
+        assert_eq!(pattern(r#"    | "^"      { tk_list.push("CARET"); }"#), "\\^");
+        assert_eq!(pattern(r#"    | "a/b"    { tk_list.push("PATH"); }"#), "a\\/b");
+        assert_eq!(pattern(r##"    | ^"#"[a-z]+    { tk_list.push("DIRECTIVE"); }"##), "^\\#[a-z]+");
+    }
+
+    #[test]
+    fn test_quoted_braces_and_bar() {
+        let (reg, act, _, _) = get_tk_act(r#"    | "{"    { tk_list.push("L_BRACE"); }"#, 1);
+        assert!(matches(&reg, "{"));
+        assert_eq!(act, "tk_list.push(L_BRACE);");
+        let (reg, act, _, _) = get_tk_act(r#"    | "}"    { tk_list.push("R_BRACE"); }"#, 1);
+        assert!(matches(&reg, "}"));
+        assert_eq!(act, "tk_list.push(R_BRACE);");
+        let bar = pattern(r#"    | "a|b"    { tk_list.push("OR"); }"#);
+        assert!(matches(&bar, "a|b"));
+        assert!(!matches(&bar, "a"));
+    }
+
+    #[test]
+    fn test_only_bare_eof_is_the_eof_rule() {
+        let (reg, _, _, is_eof) = get_tk_act(r#"    | eof    { tk_list.push("EOF"); }"#, 1);
+        assert_eq!(reg, "eof");
+        assert!(is_eof);
+        let (reg, _, _, is_eof) = get_tk_act(r#"    | "eof"    { tk_list.push("KW"); }"#, 1);
+        assert_eq!(reg, "eof");
+        assert!(!is_eof);
+    }
+
+    #[test]
+    fn test_more_than_255_rules() {
+        let mut spec = String::from("{\n}\nrule actions =\n");
+        for i in 0..300 {
+            spec += &format!("    | \"k{}\"    {{ tk_list.push(\"K{}\"); }}\n", i, i);
+        }
+        let path = std::env::temp_dir().join("lex_reader_many_rules.yal");
+        std::fs::write(&path, spec).unwrap();
+        let actions = get_line_array(path.to_str().unwrap());
+        assert_eq!(actions.len(), 300);
+        assert_eq!(actions[299].0, "k299");
+        assert!(actions[299].2 > 255);
+        let mut lines: Vec<usize> = actions.iter().map(|a| a.2).collect();
+        lines.dedup();
+        assert_eq!(lines.len(), 300);
+    }
+}
@@ -0,0 +1,203 @@
+use std::rc::Rc;
+
+use crate::derivative::derivative_dfa;
+use crate::dfa::Dfa;
+use crate::direct_afd::DirectAFD;
+use crate::extended::{has_extended, lower};
+use crate::grammar_tree::{Tree, TreeNode};
+use crate::inf_to_pos::Token;
+use crate::thompson::Nfa;
+
+/// Método con el que se construye el DFA a partir del árbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Construction {
+    /// followpos sobre el árbol (DirectAFD)
+    Direct,
+    /// NFA de Thompson y construcción de subconjuntos
+    Thompson,
+    /// Derivadas de Brzozowski sobre la regex normalizada
+    Derivative,
+}
+
+/// Arma el autómata del lexer a partir del árbol de cada regla.
+/// El orden en que se agregan las reglas es su prioridad.
+pub struct LexerBuilder {
+    rules: Vec<(usize, TreeNode)>,
+    construction: Construction,
+    allow_empty: bool,
+}
+
+impl Default for LexerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LexerBuilder {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            construction: Construction::Direct,
+            allow_empty: false,
+        }
+    }
+
+    pub fn construction(&mut self, construction: Construction) -> &mut Self {
+        self.construction = construction;
+        self
+    }
+
+    /// Permite reglas que aceptan ε; por defecto build() las rechaza porque el
+    /// scanner no avanza con un lexema vacío.
+    pub fn allow_empty(&mut self, allow: bool) -> &mut Self {
+        self.allow_empty = allow;
+        self
+    }
+
+    pub fn add_rule(&mut self, index: usize, rule: TreeNode) -> &mut Self {
+        self.rules.push((index, rule));
+        self
+    }
+
+    /// ((r1 ∘ {i1}) | (r2 ∘ {i2}) | ...) ∘ #
+    /// Cada marcador se agrega como nodo, nunca pasa por el texto del patrón.
+    pub fn build_tree(&self) -> Tree {
+        if self.rules.is_empty() {
+            panic!("The lexer needs at least one rule");
+        }
+        let marked: Vec<TreeNode> = self
+            .rules
+            .iter()
+            .map(|(index, rule)| {
+                let id = index.to_string();
+                let marker = TreeNode::new(Token::Tokener(id.clone()), None, None);
+                TreeNode::new(
+                    Token::Concat,
+                    Some(Rc::new(rule.with_trail_owner(&id))),
+                    Some(Rc::new(marker)),
+                )
+            })
+            .collect();
+        let union = marked
+            .into_iter()
+            .rev()
+            .reduce(|acc, rule| TreeNode::new(Token::Union, Some(Rc::new(rule)), Some(Rc::new(acc))))
+            .unwrap();
+        let sentinel = TreeNode::new(Token::Sentinel, None, None);
+        Tree::from_root(TreeNode::new(
+            Token::Concat,
+            Some(Rc::new(union)),
+            Some(Rc::new(sentinel)),
+        ))
+    }
+
+    /// DFA sin minimizar, con el token ganador en cada estado de aceptación.
+    pub fn build(&self) -> Dfa {
+        if !self.allow_empty {
+            self.check_nullable();
+        }
+        let tree = self.build_tree();
+        // Las derivadas manejan & y ~ directamente; los otros métodos los reciben
+        // ya reescritos como regex normal
+        let lowered = || match tree.get_root() {
+            Some(root) if has_extended(&root) => Tree::from_root(lower(&root)),
+            _ => tree.clone(),
+        };
+        match self.construction {
+            Construction::Direct => DirectAFD::new(Rc::new(lowered())).generate_afd(),
+            Construction::Thompson => Nfa::from_tree(&lowered()).to_dfa(),
+            Construction::Derivative => derivative_dfa(&tree),
+        }
+    }
+
+    fn check_nullable(&self) {
+        for (index, rule) in &self.rules {
+            let rule = lexeme_part(rule);
+            let plain = if has_extended(&rule) { lower(&rule) } else { rule };
+            if DirectAFD::new(Rc::new(Tree::from_root(plain))).root_nullable() {
+                panic!("Rule at line {} matches the empty string", index);
+            }
+        }
+    }
+}
+
+// Lo que la regla consume como lexema: r en r/s, con ^ como ε. Los marcadores
+// no son nullable en el árbol, así que ^a* o a*/b se verían como no vacías.
+fn lexeme_part(rule: &TreeNode) -> TreeNode {
+    if *rule.get_value() == Token::Bol {
+        return TreeNode::new(Token::Empty, None, None);
+    }
+    // r/s es (r ∘ marcador) ∘ s
+    if let (Token::Concat, Some(left)) = (rule.get_value(), rule.get_left()) {
+        let marker = left.get_right();
+        let is_trail = matches!(marker.as_deref().map(|m| m.get_value()), Some(Token::Trail(_)));
+        if *left.get_value() == Token::Concat && is_trail {
+            return lexeme_part(&left.get_left().unwrap());
+        }
+    }
+    TreeNode::new(
+        rule.get_value().clone(),
+        rule.get_left().map(|l| Rc::new(lexeme_part(&l))),
+        rule.get_right().map(|r| Rc::new(lexeme_part(&r))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::gen_rules;
+    use crate::regex_parser::parse_rule;
+    use crate::token_identifier::asignar_token;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    #[should_panic(expected = "Rule at line 5 matches the empty string")]
+    fn test_nullable_rule_is_rejected() {
+        let path = std::env::temp_dir().join("lexer_builder_nullable.yal");
+        let spec = "{\n}\nrule actions =\n    [a-z]+    { tk_list.push(\"ID\"); }\n    | [0-9]*  { tk_list.push(\"INT\"); }\n";
+        std::fs::write(&path, spec).unwrap();
+        let (rules, _actions, _eof_token) = gen_rules(path.to_str().unwrap());
+        let mut builder = LexerBuilder::new();
+        for (index, rule) in rules {
+            builder.add_rule(index, rule);
+        }
+        builder.build();
+    }
+
+    fn rejected(pattern: &str) -> bool {
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule(pattern));
+        catch_unwind(AssertUnwindSafe(|| builder.build())).is_err()
+    }
+
+    #[test]
+    fn test_zero_width_rules_are_rejected() {
+        // ^ y el contexto s no consumen, el lexema es vacío
+        assert!(rejected("^a*"));
+        assert!(rejected("a*/b*"));
+        assert!(rejected("a*/b"));
+        assert!(rejected("^(a|b)?/c"));
+        assert!(!rejected("^a+"));
+        assert!(!rejected("a+/b*"));
+        assert!(!rejected("^#[a-z]+"));
+    }
+
+    #[test]
+    fn test_nullable_rule_can_be_allowed() {
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule("[a-z]*"));
+        builder.allow_empty(true);
+        assert!(builder.build().accept.contains_key(&'A'));
+    }
+
+    #[test]
+    fn test_rule_index_above_255() {
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(300, parse_rule("[a-z]+"));
+        builder.add_rule(256, parse_rule("[0-9]+"));
+        let dfa = builder.build();
+        assert_eq!(dfa.token_list, vec!["300", "256"]);
+        assert_eq!(asignar_token(&dfa, "abc", false), "300");
+        assert_eq!(asignar_token(&dfa, "42", false), "256");
+    }
+}
@@ -1,80 +1,75 @@
+mod alphabet;
+mod backtracking;
+mod compress;
+mod derivative;
+mod dfa;
 mod direct_afd;
+mod equivalence;
+mod extended;
+mod generator;
 mod grammar_tree;
 mod inf_to_pos;
+mod lexer_builder;
 mod minimize;
+mod product;
+mod reference;
+mod regex_parser;
+mod rule_analysis;
+mod spec_test;
+mod thompson;
 mod token_identifier;
 mod view;
 
-use crate::direct_afd::DirectAFD;
-use crate::inf_to_pos::Token;
+use crate::dfa::Dfa;
+use crate::grammar_tree::TreeNode;
+use crate::lexer_builder::{Construction, LexerBuilder};
 use minimize::minimize_dfa;
-use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
-use token_identifier::asignar_token;
+use std::collections::HashMap;
+use std::env;
+use token_identifier::{asignar_token, fin_de_lexema};
 mod compile;
 mod lex_reader;
 mod reader;
 mod utilities;
 
-fn generate(
-    regx: String,
-) -> (
-    HashMap<char, HashMap<String, char>>,
-    HashSet<char>,
-    char,
-    Vec<String>,
-) {
-    let postfix: Vec<Token> = inf_to_pos::inf_to_pos(&regx);
-    let mut gtree = grammar_tree::Tree::new();
-    let root = gtree.generate(postfix);
-    // println!(
-    //             "Árbol Sintáctico:\n{}",
-    //             (*root).clone().print_tree(0, "root\n")
-    //         );
-    let gtree_ref = Rc::new(gtree);
-    let mut afd = DirectAFD::new(gtree_ref);
-    afd.generate_afd();
-    let (state_map, acceptance_states, token_list) = afd.create_states();
-    let (minimized_map, minimized_accept_states, minimized_start) =
-        minimize_dfa(&state_map, &acceptance_states);
-    (
-        minimized_map,
-        minimized_accept_states,
-        minimized_start,
-        token_list,
-    )
-}
-
-fn get_token_type(
-    input: String,
-    minimized_map: &HashMap<char, HashMap<String, char>>,
-    minimized_accept_states: &HashSet<char>,
-    minimized_start: char,
-    token_list: &Vec<String>,
-) -> String {
-    let mini = asignar_token(
-        &minimized_map,
-        &input,
-        minimized_start,
-        &minimized_accept_states,
-        &token_list,
-    );
+fn generate(rules: Vec<(usize, TreeNode)>) -> Dfa {
+    let mut builder = LexerBuilder::new();
+    for (index, rule) in &rules {
+        builder.add_rule(*index, rule.clone());
+    }
+    let dfa = minimize_dfa(&builder.build());
+    for shadowed in rule_analysis::find_shadowed(&rules, &dfa) {
+        if shadowed.by.is_empty() {
+            eprintln!("Warning: rule at line {} never matches any input", shadowed.line);
+        } else {
+            eprintln!(
+                "Warning: rule at line {} can never win, it is shadowed by line(s) {:?}",
+                shadowed.line, shadowed.by
+            );
+        }
+    }
+    dfa
+}
+
+fn get_token_type(input: String, minimized_dfa: &Dfa, inicio_de_linea: bool) -> String {
+    let mini = asignar_token(minimized_dfa, &input, inicio_de_linea);
     mini
 }
 
 fn simulate(
     input: String,
-    minimized_map: HashMap<char, HashMap<String, char>>,
-    minimized_accept_states: HashSet<char>,
-    minimized_start: char,
-    token_list: Vec<String>,
+    minimized_dfa: Dfa,
+    eof_token: Option<String>,
 ) -> Vec<String> {
     let mut tk_list: Vec<String> = Vec::new();
     let len = input.len();
     let mut last_start = 0;
-    let mut condition = false;
+    let mut condition = len == 0;
 
     while !condition {
+        // Las reglas con ^ solo aplican al inicio de la entrada o después de un salto de línea
+        let inicio_de_linea =
+            last_start == 0 || input.chars().nth(last_start - 1) == Some('\n');
         let mut lexem = String::new();
         let mut greedy_match = String::new();
         let mut greedy_end = 0;
@@ -83,10 +78,8 @@ fn simulate(
             lexem.push(c);
             let cmatch = get_token_type(
                 lexem.to_string(),
-                &minimized_map,
-                &minimized_accept_states,
-                minimized_start,
-                &token_list,
+                &minimized_dfa,
+                inicio_de_linea,
             );
             if cmatch != "UNKNOWN" {
                 greedy_match = cmatch;
@@ -104,6 +97,12 @@ fn simulate(
                 .take(greedy_end - last_start)
                 .collect();
             // println!("FINAL ({}-{}) Lex: \"{}\", match: {:?}", last_start, greedy_end,biggest_lex, greedy_match);
+            // Con contexto posterior (r/s) solo se consume la parte r
+            if let Some(corte) = fin_de_lexema(&minimized_dfa, &biggest_lex, &greedy_match, inicio_de_linea)
+                .filter(|&corte| corte > 0)
+            {
+                greedy_end = last_start + corte;
+            }
         }
         // println!("GREEDY MATCH{}",greedy_match);
         if greedy_match == "UNKNOWN" {
@@ -116,41 +115,312 @@ fn simulate(
             condition = true;
         }
     }
+    // La acción de eof se ejecuta una sola vez al acabar la entrada
+    if let Some(eof) = eof_token {
+        tk_list.push(eof);
+    }
     tk_list
 }
 
-fn main() {
-    let input = r"while 1.5 < -6 { 
-        num = 65.
-    }";
-    let (reg, actions) = compile::gen_reg();
-    let copy = reg.clone();
-    let (minimized_map, minimized_accept_states, minimized_start, token_list) = generate(reg);
-    let toks = simulate(
-        input.to_string(),
-        minimized_map,
-        minimized_accept_states,
-        minimized_start,
-        token_list,
+// Vista de depuración: la forma postfix de inf_to_pos junto al árbol del parser
+fn print_postfix(regx: &str) {
+    let postfix = inf_to_pos::inf_to_pos(regx);
+    println!("Postfix: {:?}", postfix);
+    let mut postfix_tree = grammar_tree::Tree::new();
+    let root = postfix_tree.generate(postfix);
+    println!("Árbol desde postfix:\n{}", (*root).clone().print_tree(0, "root\n"));
+    let gtree = regex_parser::parse(regx);
+    println!(
+        "Árbol Sintáctico:\n{}",
+        (*gtree.get_root().unwrap()).clone().print_tree(0, "root\n")
     );
-    let mut executable: String = "fn act(toks: Vec<String>){
-    let tk_list:Vec<String>= Vec::new();
-    for t in toks{"
-        .to_string();
-    for (key, value) in &actions {
-        executable += &format!("if t==\"{}\" {{ {} }}\n", key, value);
+}
+
+// Construye el lexer del spec con cada método; ya minimizados los DFAs
+// tienen que ser iguales salvo por los nombres de los estados
+fn crosscheck(lex: &str) {
+    let (rules, _actions, _eof_token) = compile::gen_rules(lex);
+    let mut builder = LexerBuilder::new();
+    for (index, rule) in rules {
+        builder.add_rule(index, rule);
+    }
+    let direct = minimize_dfa(&builder.build());
+    for construction in [Construction::Thompson, Construction::Derivative] {
+        builder.construction(construction);
+        let other = minimize_dfa(&builder.build());
+        if !dfa::isomorphic(&direct, &other) {
+            panic!(
+                "DirectAFD and {:?} disagree on {}: {} vs {} states",
+                construction,
+                lex,
+                direct.transitions.len(),
+                other.transitions.len()
+            );
+        }
+    }
+    // Hopcroft y Brzozowski deben llegar al mismo DFA, y sin estados equivalentes
+    let brzozowski = minimize::minimize_brzozowski(&builder.build());
+    if !dfa::isomorphic(&direct, &brzozowski) {
+        panic!("Hopcroft and Brzozowski minimization disagree on {}", lex);
+    }
+    if !minimize::verify_minimal(&direct) {
+        panic!("The minimized DFA for {} still has equivalent states", lex);
+    }
+    println!("{}: ok, {} states", lex, direct.transitions.len());
+}
+
+// Un spec .yal o, si no existe el archivo, un solo patrón
+fn load_lexer(source: &str) -> Dfa {
+    let mut builder = LexerBuilder::new();
+    let actions = if std::path::Path::new(source).exists() {
+        let (rules, actions, _eof_token) = compile::gen_rules(source);
+        for (index, rule) in rules {
+            builder.add_rule(index, rule);
+        }
+        actions
+    } else {
+        // Un patrón suelto se compara como lenguaje, ε es una cadena más
+        builder.allow_empty(true);
+        builder.add_rule(0, regex_parser::parse_rule(source));
+        HashMap::from([(0, "MATCH".to_string())])
+    };
+    let mut dfa = minimize_dfa(&builder.build());
+    // Los ids de token son números de línea, entre specs se comparan las acciones
+    let rename = |token: &mut String| {
+        if let Some(action) = token.parse::<usize>().ok().and_then(|id| actions.get(&id)) {
+            *token = action.trim().to_string();
+        }
+    };
+    dfa.accept.values_mut().for_each(rename);
+    dfa.token_list.iter_mut().for_each(rename);
+    for tokens in dfa.trail.values_mut() {
+        *tokens = tokens
+            .drain()
+            .map(|mut token| {
+                rename(&mut token);
+                token
+            })
+            .collect();
+    }
+    // Dos reglas con la misma acción quedan con la prioridad de la primera
+    let mut seen = std::collections::HashSet::new();
+    dfa.token_list.retain(|token| seen.insert(token.clone()));
+    dfa
+}
+
+fn equiv(left: &str, right: &str) {
+    match equivalence::find_difference(&load_lexer(left), &load_lexer(right)) {
+        None => println!("Equivalent"),
+        Some(diff) => println!(
+            "Not equivalent: {:?}{} -> {:?} vs {:?}",
+            diff.input,
+            if diff.inicio_de_linea { " (at line start)" } else { "" },
+            diff.left,
+            diff.right
+        ),
+    }
+}
+
+// Operación de lenguaje entre dos lexers (o patrones) y la cadena más corta del
+// resultado, p. ej. `lang and "[a-z]+" "if|while"` para ver si chocan
+fn lang(op: &str, left: &str, right: Option<&str>) {
+    let left = load_lexer(left);
+    let right = right.map(load_lexer);
+    let result = match (op, &right) {
+        ("and", Some(right)) => left.intersection(right),
+        ("or", Some(right)) => left.union(right),
+        ("minus", Some(right)) => left.difference(right),
+        ("not", None) => left.complement("NOT"),
+        _ => panic!("Usage: lang <and|or|minus> <a> <b> | lang not <a>"),
+    };
+    if result.is_empty() {
+        println!("Empty language");
+        return;
+    }
+    if let Some(shortest) = equivalence::shortest_match(&result) {
+        println!(
+            "Shortest string: {:?}{} -> {:?}",
+            shortest.input,
+            if shortest.inicio_de_linea { " (at line start)" } else { "" },
+            shortest.left.unwrap_or_default()
+        );
+    }
+}
+
+// Pares de reglas del spec que aceptan un mismo lexema y cuál gana
+fn ambiguities(lex: &str) {
+    let (rules, actions, _eof_token) = compile::gen_rules(lex);
+    let mut builder = LexerBuilder::new();
+    for (index, rule) in &rules {
+        builder.add_rule(*index, rule.clone());
+    }
+    let dfa = minimize_dfa(&builder.build());
+    let describe = |line: usize| match actions.get(&line) {
+        Some(action) => format!("line {} {{{}}}", line, action.trim()),
+        None => format!("line {}", line),
+    };
+    let report = rule_analysis::find_ambiguities(&rules, &dfa);
+    for ambiguity in &report {
+        let winner = match ambiguity.winner.parse::<usize>() {
+            Ok(line) => describe(line),
+            Err(_) => ambiguity.winner.clone(),
+        };
+        println!(
+            "{} and {} both match {:?}{} -> {}",
+            describe(ambiguity.first),
+            describe(ambiguity.second),
+            ambiguity.lexeme,
+            if ambiguity.inicio_de_linea { " (at line start)" } else { "" },
+            winner
+        );
+    }
+    println!("{} overlapping pair(s)", report.len());
+}
+
+// Lenguaje de cada regla del spec: finito o no, longitudes y ejemplos
+fn stats(lex: &str) {
+    let (rules, actions, _eof_token) = compile::gen_rules(lex);
+    for (line, rule) in &rules {
+        let stats = rule_analysis::rule_stats(*line, rule, 5);
+        let action = actions.get(line).map(|a| a.trim()).unwrap_or_default();
+        let max = match stats.max_len {
+            Some(max) => max.to_string(),
+            None => "unbounded".to_string(),
+        };
+        println!(
+            "line {} {{{}}}: {}, length {}..{}, examples {:?}",
+            line,
+            action,
+            if stats.finite { "finite" } else { "infinite" },
+            stats.min_len,
+            max,
+            stats.examples
+        );
     }
+}
+
+// Cuánto puede retroceder el scanner con maximal munch y por qué estados
+fn backtrack(source: &str) {
+    let report = backtracking::backtracking(&load_lexer(source));
+    match report.distance {
+        Some(0) => println!("Backtrack-free"),
+        Some(distance) => println!(
+            "Worst-case backup: {} char(s) through states {:?}",
+            distance, report.states
+        ),
+        None => println!(
+            "Unbounded backup: non-accepting cycle through states {:?}",
+            report.states
+        ),
+    }
+}
+
+// Entrada al azar válida para el lexer, con la semilla dada para poder repetirla
+fn fuzz(source: &str, seed: &str, count: Option<&String>, separator: Option<&String>) {
+    let seed: u64 = seed.parse().expect("The seed must be a number");
+    let count: usize = count.map_or(20, |c| c.parse().expect("The count must be a number"));
+    let separator = separator.map_or(" ".to_string(), |s| s.replace("\\n", "\n"));
+    let mut generator = generator::Generator::new(&load_lexer(source), seed);
+    let sequence = generator.sequence(count, &separator);
+    let text: Vec<&str> = sequence.iter().map(|(_, lexeme)| lexeme.as_str()).collect();
+    println!("{}", text.join(&separator));
+    // Los tokens son la acción de la regla; las que no emiten nada se saltan
+    for (token, lexeme) in &sequence {
+        let name = compile::token_name(token).unwrap_or_else(|| "(skip)".to_string());
+        println!("{:?} -> {}", lexeme, name);
+    }
+}
+
+// Compara el matcher de referencia con los DFAs sobre lexers al azar
+fn difftest(seed: &str, cases: Option<&String>) {
+    let seed: u64 = seed.parse().expect("The seed must be a number");
+    let cases: usize = cases.map_or(200, |c| c.parse().expect("The case count must be a number"));
+    match reference::differential(seed, cases) {
+        Ok(compared) => println!("ok, {} inputs compared", compared),
+        Err(case) => panic!("Mismatch: {}", case),
+    }
+}
 
-    executable += "   }\n     println!(\"{:?}\",tk_list); \n}\nfn main() {\n";
-    executable += &format!("let reg = r\"{}\";\n", copy);
-    executable+="let (minimized_map, minimized_accept_states,minimized_start, token_list) = generate(reg);
-    let toks = simulate(input.to_string(), minimized_map, minimized_accept_states, minimized_start, token_list);
-    let mut reslut: Vec<String> = Vec::new();
-    for t in toks{
-        act(t);
+// Corre las pruebas de un spec: un directorio de casos o los casos del .yal
+fn test(path: &str) {
+    let (_passed, failed) = spec_test::run_tests(path);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+// Escribe src/bin/compiler.rs para otro spec, con tablas, código directo o
+// tablas comprimidas, e imprime el tamaño de las tablas
+fn emit(lex: &str, emission: &str) {
+    let emission = match emission {
+        "tables" => compile::Emission::Tables,
+        "direct" => compile::Emission::DirectCoded,
+        "compressed" => compile::Emission::Compressed,
+        _ => panic!("Usage: emit <spec> <tables|direct|compressed>"),
+    };
+    let (rules, actions, eof_token) = compile::gen_rules(lex);
+    let minimized_dfa = generate(rules);
+    let sizes = compile::scanner_table_sizes(&minimized_dfa);
+    println!(
+        "Transition tables: dense {} bytes, ranges {} bytes, compressed {} bytes",
+        sizes.dense, sizes.ranges, sizes.compressed
+    );
+    let scanner = compile::emit_scanner(lex, &minimized_dfa, &actions, eof_token, emission);
+    compile::creat_file(scanner).expect("Cannot write src/bin/compiler.rs");
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 3 && args[1] == "postfix" {
+        print_postfix(&args[2]);
+        return;
+    }
+    if args.len() == 3 && args[1] == "crosscheck" {
+        crosscheck(&args[2]);
+        return;
+    }
+    if args.len() == 4 && args[1] == "emit" {
+        emit(&args[2], &args[3]);
+        return;
     }
-}";
-    compile::creat_file(executable);
+    if args.len() == 3 && args[1] == "test" {
+        test(&args[2]);
+        return;
+    }
+    if (3..=4).contains(&args.len()) && args[1] == "difftest" {
+        difftest(&args[2], args.get(3));
+        return;
+    }
+    if (4..=6).contains(&args.len()) && args[1] == "fuzz" {
+        fuzz(&args[2], &args[3], args.get(4), args.get(5));
+        return;
+    }
+    if args.len() == 3 && args[1] == "backtrack" {
+        backtrack(&args[2]);
+        return;
+    }
+    if args.len() == 3 && args[1] == "stats" {
+        stats(&args[2]);
+        return;
+    }
+    if args.len() == 3 && args[1] == "ambiguities" {
+        ambiguities(&args[2]);
+        return;
+    }
+    if args.len() == 4 && args[1] == "equiv" {
+        equiv(&args[2], &args[3]);
+        return;
+    }
+    if (args.len() == 4 || args.len() == 5) && args[1] == "lang" {
+        lang(&args[2], &args[3], args.get(4).map(|s| s.as_str()));
+        return;
+    }
+    let lex = "./test.yal";
+    let (rules, actions, eof_token) = compile::gen_rules(lex);
+    let minimized_dfa = generate(rules);
+    // El scanner generado lleva sus propias tablas, no depende de este crate
+    let scanner = compile::emit_scanner(lex, &minimized_dfa, &actions, eof_token, compile::Emission::Tables);
+    compile::creat_file(scanner).expect("Cannot write src/bin/compiler.rs");
 }
 // fn main() {
 //     // let regex = r"(a(b|c?d+)[A-Z][0-9]*|x(yz)*z)?w+";
@@ -205,3 +475,56 @@ fn main() {
 //         );
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex_parser::parse_rule;
+
+    fn run(rules: &[(usize, &str)], input: &str, eof: Option<&str>) -> Vec<String> {
+        let rules = rules
+            .iter()
+            .map(|(index, pattern)| (*index, parse_rule(pattern)))
+            .collect();
+        simulate(input.to_string(), generate(rules), eof.map(|t| t.to_string()))
+    }
+
+    #[test]
+    fn test_trailing_context_in_simulate() {
+        // El lexema de r/s termina antes de s y el scanner sigue desde ahí
+        let rules = [(4, "[0-9]+/\\.\\."), (7, "[0-9]+"), (18, "\\.\\.")];
+        assert_eq!(run(&rules, "1..5", None), vec!["4", "18", "7"]);
+        assert_eq!(run(&rules, "12..", None), vec!["4", "18"]);
+
+        // Con varias marcas posibles se corta en la última: "aaa" y luego "b"
+        assert_eq!(run(&[(1, "a+/a*b"), (2, "b")], "aaab", None), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_line_anchor_and_eof() {
+        let rules = [(1, "^\\#[a-z]+"), (2, "\\#"), (3, "[a-z]+"), (4, " |\n"), (5, "\\^")];
+        // ^#[a-z]+ solo gana al inicio de la entrada o después de \n
+        assert_eq!(run(&rules, "#ab #ab\n#ab", None), vec!["1", "4", "2", "3", "4", "1"]);
+        // Un ^ escapado es el caracter, no el ancla
+        assert_eq!(run(&rules, "^ab", None), vec!["5", "3"]);
+        // eof sale una sola vez, al final, aunque la entrada esté vacía
+        assert_eq!(run(&rules, "ab #", Some("9")), vec!["3", "4", "2", "9"]);
+        assert_eq!(run(&rules, "", Some("9")), vec!["9"]);
+    }
+
+    #[test]
+    fn test_quoted_eof_is_a_keyword() {
+        // "eof" entre comillas es una palabra clave; solo eof sin comillas es el fin de entrada
+        let spec = "{\n}\nrule actions =\n    | \"eof\"      { tk_list.push(\"KW\"); }\n    | [a-z]+     { tk_list.push(\"ID\"); }\n    | \" \"        { }\n    | eof        { tk_list.push(\"EOF\"); }\n";
+        let path = std::env::temp_dir().join("main_quoted_eof.yal");
+        std::fs::write(&path, spec).unwrap();
+        let (rules, _actions, eof_token) = compile::gen_rules(path.to_str().unwrap());
+        assert_eq!(eof_token, Some(7));
+        let lines: Vec<usize> = rules.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![4, 5, 6]);
+        assert_eq!(
+            simulate("eof ab".to_string(), generate(rules), eof_token.map(|t| t.to_string())),
+            vec!["4", "6", "5", "7"]
+        );
+    }
+}
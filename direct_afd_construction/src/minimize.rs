@@ -1,128 +1,492 @@
-use std::collections::{HashMap, HashSet};
-
-/// Minimiza un DFA usando el algoritmo de Hopcroft.
-/// Devuelve (minimized_dfa, minimized_accept_states, minimized_start_state).
-/// Se asume que el estado inicial original es `'A'`.
-pub fn minimize_dfa(
-    dfa: &HashMap<char, HashMap<String, char>>,
-    accept_states: &HashSet<char>,
-) -> (HashMap<char, HashMap<String, char>>, HashSet<char>, char) {
-    // Construir alfabeto
-    let mut alphabet = HashSet::new();
-    for trans in dfa.values() {
-        for sym in trans.keys() {
-            alphabet.insert(sym.clone());
-        }
-    }
-
-    // Completar DFA con estado sink '?'
-    let sink = '?';
-    let mut complete = dfa.clone();
-    complete.entry(sink).or_default();
-    for (&state, trans) in dfa {
-        let row = complete.entry(state).or_default();
-        for sym in &alphabet {
-            row.entry(sym.clone()).or_insert(sink);
-        }
-    }
-    for sym in &alphabet {
-        complete.get_mut(&sink).unwrap().insert(sym.clone(), sink);
-    }
-
-    // Partición inicial P = {F, Q\\F}
-    let all_states: HashSet<char> = complete.keys().cloned().collect();
-    let f = accept_states.clone();
-    let non_f: HashSet<char> = all_states.difference(&f).cloned().collect();
-    let mut P = Vec::new();
-    if !f.is_empty() {
-        P.push(f.clone());
-    }
-    if !non_f.is_empty() {
-        P.push(non_f.clone());
-    }
-
-    // Conjunto de trabajo W
-    let mut W = vec![P[0].clone()];
-
-    // Hopcroft refinement
-    while let Some(A) = W.pop() {
-        for sym in &alphabet {
-            let mut X = HashSet::new();
-            for &s in &all_states {
-                if complete
-                    .get(&s)
-                    .and_then(|m| m.get(sym))
-                    .copied()
-                    .filter(|t| A.contains(t))
-                    .is_some()
-                {
-                    X.insert(s);
+use crate::dfa::Dfa;
+use crate::utilities::next_state_name;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+// Partición refinable: los estados de cada bloque quedan contiguos en `elems`,
+// así partir un bloque solo mueve los estados marcados al principio de su rango.
+struct Partition {
+    elems: Vec<usize>,
+    pos: Vec<usize>,
+    block_of: Vec<usize>,
+    start: Vec<usize>,
+    end: Vec<usize>,
+    marked: Vec<usize>,
+}
+
+impl Partition {
+    fn new(groups: Vec<Vec<usize>>, n: usize) -> Self {
+        let mut partition = Partition {
+            elems: Vec::with_capacity(n),
+            pos: vec![0; n],
+            block_of: vec![0; n],
+            start: Vec::new(),
+            end: Vec::new(),
+            marked: Vec::new(),
+        };
+        for group in groups {
+            let block = partition.start.len();
+            partition.start.push(partition.elems.len());
+            for state in group {
+                partition.pos[state] = partition.elems.len();
+                partition.block_of[state] = block;
+                partition.elems.push(state);
+            }
+            partition.end.push(partition.elems.len());
+            partition.marked.push(0);
+        }
+        partition
+    }
+
+    fn len(&self) -> usize {
+        self.start.len()
+    }
+
+    fn size(&self, block: usize) -> usize {
+        self.end[block] - self.start[block]
+    }
+
+    fn states(&self, block: usize) -> &[usize] {
+        &self.elems[self.start[block]..self.end[block]]
+    }
+
+    // Mueve el estado a la zona marcada de su bloque
+    fn mark(&mut self, state: usize) -> usize {
+        let block = self.block_of[state];
+        let target = self.start[block] + self.marked[block];
+        if self.pos[state] >= target {
+            let other = self.elems[target];
+            let from = self.pos[state];
+            self.elems.swap(from, target);
+            self.pos[other] = from;
+            self.pos[state] = target;
+            self.marked[block] += 1;
+        }
+        block
+    }
+
+    // Separa los marcados en un bloque nuevo; None si el bloque no se parte
+    fn split(&mut self, block: usize) -> Option<usize> {
+        let marked = self.marked[block];
+        self.marked[block] = 0;
+        if marked == 0 || marked == self.size(block) {
+            return None;
+        }
+        let new_block = self.len();
+        let mid = self.start[block] + marked;
+        self.start.push(self.start[block]);
+        self.end.push(mid);
+        self.marked.push(0);
+        self.start[block] = mid;
+        for i in self.start[new_block]..mid {
+            self.block_of[self.elems[i]] = new_block;
+        }
+        Some(new_block)
+    }
+}
+
+/// Minimiza un DFA usando el algoritmo de Hopcroft, después de quitarle los
+/// estados inalcanzables y muertos. Los estados del resultado se nombran desde
+/// 'A' en orden de recorrido a partir de su estado inicial.
+pub fn minimize_dfa(dfa: &Dfa) -> Dfa {
+    let original = &dfa.prune();
+    // Estados como índices; el último es el sink que completa el DFA
+    let mut names: Vec<char> = original.transitions.keys().cloned().collect();
+    for row in original.transitions.values() {
+        names.extend(row.values().cloned());
+    }
+    names.sort();
+    names.dedup();
+    let sink = names.len();
+    let index: HashMap<char, usize> = names.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let n = sink + 1;
+
+    let mut alphabet: Vec<String> = original
+        .transitions
+        .values()
+        .flat_map(|row| row.keys().cloned())
+        .collect();
+    alphabet.sort();
+    alphabet.dedup();
+
+    let mut delta = vec![vec![sink; alphabet.len()]; n];
+    let mut inverse: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); n]; alphabet.len()];
+    for (a, sym) in alphabet.iter().enumerate() {
+        for (s, row) in delta.iter_mut().enumerate() {
+            let target = names
+                .get(s)
+                .and_then(|name| original.transitions.get(name))
+                .and_then(|t| t.get(sym))
+                .map(|dest| index[dest])
+                .unwrap_or(sink);
+            row[a] = target;
+            inverse[a][target].push(s);
+        }
+    }
+
+    // Partición inicial: estados con el mismo token aceptado y las mismas marcas de r/s
+    let mut signatures: HashMap<(Option<&String>, Vec<&String>), Vec<usize>> = HashMap::new();
+    for s in 0..n {
+        let name = names.get(s);
+        let mut trail: Vec<&String> = name
+            .and_then(|name| original.trail.get(name))
+            .map(|t| t.iter().collect())
+            .unwrap_or_default();
+        trail.sort();
+        let accept = name.and_then(|name| original.accept.get(name));
+        signatures.entry((accept, trail)).or_default().push(s);
+    }
+    let mut groups: Vec<Vec<usize>> = signatures.into_values().collect();
+    groups.sort();
+    let mut partition = Partition::new(groups, n);
+
+    // Todos los bloques iniciales menos el más grande entran como divisores
+    let largest = (0..partition.len())
+        .max_by_key(|&b| partition.size(b))
+        .unwrap();
+    let mut waiting: Vec<(usize, usize)> = Vec::new();
+    let mut in_waiting: Vec<Vec<bool>> = Vec::new();
+    for block in 0..partition.len() {
+        in_waiting.push(vec![block != largest; alphabet.len()]);
+        if block != largest {
+            waiting.extend((0..alphabet.len()).map(|a| (block, a)));
+        }
+    }
+
+    while let Some((splitter, a)) = waiting.pop() {
+        in_waiting[splitter][a] = false;
+        let mut touched = Vec::new();
+        let targets: Vec<usize> = partition.states(splitter).to_vec();
+        for target in targets {
+            for &s in &inverse[a][target] {
+                let block = partition.mark(s);
+                if partition.marked[block] == 1 {
+                    touched.push(block);
+                }
+            }
+        }
+        for block in touched {
+            let Some(new_block) = partition.split(block) else {
+                continue;
+            };
+            in_waiting.push(vec![false; alphabet.len()]);
+            let smaller = if partition.size(new_block) <= partition.size(block) {
+                new_block
+            } else {
+                block
+            };
+            let block_waiting = in_waiting[block].clone();
+            for (c, was_waiting) in block_waiting.into_iter().enumerate() {
+                let add = if was_waiting { new_block } else { smaller };
+                if !in_waiting[add][c] {
+                    in_waiting[add][c] = true;
+                    waiting.push((add, c));
                 }
             }
+        }
+    }
 
-            let mut new_P = Vec::new();
-            for Y in P.drain(..) {
-                let intersection: HashSet<char> = Y.intersection(&X).cloned().collect();
-                let difference: HashSet<char> = Y.difference(&X).cloned().collect();
-                if !intersection.is_empty() && !difference.is_empty() {
-                    new_P.push(intersection.clone());
-                    new_P.push(difference.clone());
-                    if let Some(pos) = W.iter().position(|w| *w == Y) {
-                        W.remove(pos);
-                        W.push(intersection);
-                        W.push(difference);
-                    } else if intersection.len() <= difference.len() {
-                        W.push(intersection);
-                    } else {
-                        W.push(difference);
-                    }
-                } else {
-                    new_P.push(Y);
+    // Nombres desde 'A' recorriendo desde los iniciales; el bloque del sink
+    // solo se nombra si algún estado inicial cayó en él
+    let sink_block = partition.block_of[sink];
+    let start_block = partition.block_of[index[&original.start]];
+    let bol_block = original.bol_start.map(|s| partition.block_of[index[&s]]);
+    let mut order: Vec<usize> = [start_block].into_iter().chain(bol_block).collect();
+    let mut named: Vec<usize> = Vec::new();
+    let mut block_name: HashMap<usize, char> = HashMap::new();
+    let mut next_name = 'A';
+    let mut i = 0;
+    while i < order.len() {
+        let block = order[i];
+        i += 1;
+        if block_name.contains_key(&block) {
+            continue;
+        }
+        block_name.insert(block, next_name);
+        named.push(block);
+        next_name = next_state_name(next_name);
+        let repr = partition.states(block)[0];
+        for &dest in &delta[repr] {
+            let target = partition.block_of[dest];
+            if target != sink_block && block != sink_block {
+                order.push(target);
+            }
+        }
+    }
+
+    // Construir DFA minimizado, sin transiciones hacia el bloque del sink
+    let mut minimized = Dfa::new(block_name[&start_block], original.token_list.clone());
+    for &block in &named {
+        let repr = partition.states(block)[0];
+        let new_state = block_name[&block];
+        let mut row = HashMap::new();
+        if block != sink_block {
+            for (a, sym) in alphabet.iter().enumerate() {
+                let target = partition.block_of[delta[repr][a]];
+                if target != sink_block {
+                    row.insert(sym.clone(), block_name[&target]);
                 }
             }
-            P = new_P;
         }
+        minimized.transitions.insert(new_state, row);
+        if let Some(name) = names.get(repr) {
+            if let Some(token) = original.accept.get(name) {
+                minimized.accept.insert(new_state, token.clone());
+            }
+            if let Some(trail) = original.trail.get(name) {
+                minimized.trail.insert(new_state, trail.clone());
+            }
+        }
+    }
+    minimized.bol_start = bol_block.map(|b| block_name[&b]);
+
+    // println!("Mapa de estados: {:?}", minimized.transitions);
+    minimized
+}
+
+// Autómata sobre enteros con etiquetas de texto, para invertir y determinizar
+type Edges = Vec<Vec<(String, usize)>>;
+
+fn reverse(edges: &Edges) -> Edges {
+    let mut reversed: Edges = vec![Vec::new(); edges.len()];
+    for (from, row) in edges.iter().enumerate() {
+        for (label, to) in row {
+            reversed[*to].push((label.clone(), from));
+        }
+    }
+    reversed
+}
+
+// Construcción de subconjuntos; el estado 0 del resultado es el inicial
+fn determinize(edges: &Edges, initial: Vec<usize>, finals: &[usize]) -> (Edges, Vec<usize>) {
+    let mut initial = initial;
+    initial.sort();
+    initial.dedup();
+    let mut sets: Vec<Vec<usize>> = vec![initial.clone()];
+    let mut index: HashMap<Vec<usize>, usize> = HashMap::from([(initial, 0)]);
+    let mut result: Edges = Vec::new();
+    let mut i = 0;
+    while i < sets.len() {
+        let mut moves: HashMap<&String, Vec<usize>> = HashMap::new();
+        for &state in &sets[i] {
+            for (label, to) in &edges[state] {
+                moves.entry(label).or_default().push(*to);
+            }
+        }
+        let mut labels: Vec<&String> = moves.keys().cloned().collect();
+        labels.sort();
+        let mut row = Vec::new();
+        for label in labels {
+            let mut target = moves.remove(label).unwrap();
+            target.sort();
+            target.dedup();
+            let next = *index.entry(target.clone()).or_insert_with(|| {
+                sets.push(target);
+                sets.len() - 1
+            });
+            row.push((label.clone(), next));
+        }
+        result.push(row);
+        i += 1;
+    }
+    let new_finals = (0..sets.len())
+        .filter(|&s| sets[s].iter().any(|st| finals.contains(st)))
+        .collect();
+    (result, new_finals)
+}
+
+/// Minimización de Brzozowski: invertir, determinizar, invertir, determinizar.
+/// Los tokens y marcas de r/s se codifican como transiciones a un único estado
+/// final, y los dos estados iniciales (normal y ^) cuelgan de un inicio nuevo;
+/// así la minimización del lenguaje respeta los atributos de cada estado.
+pub fn minimize_brzozowski(original: &Dfa) -> Dfa {
+    let mut names: Vec<char> = original.transitions.keys().cloned().collect();
+    for row in original.transitions.values() {
+        names.extend(row.values().cloned());
+    }
+    names.push(original.start);
+    names.sort();
+    names.dedup();
+    let index: HashMap<char, usize> = names.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let initial = names.len();
+    let last = names.len() + 1;
+
+    let mut edges: Edges = vec![Vec::new(); names.len() + 2];
+    for (&state, row) in &original.transitions {
+        for (sym, dest) in row {
+            edges[index[&state]].push((sym.clone(), index[dest]));
+        }
+    }
+    for (state, token) in &original.accept {
+        edges[index[state]].push((format!("@accept:{}", token), last));
+    }
+    for (state, trail) in &original.trail {
+        for token in trail {
+            edges[index[state]].push((format!("@trail:{}", token), last));
+        }
+    }
+    edges[initial].push(("@start".to_string(), index[&original.start]));
+    if let Some(bol) = original.bol_start {
+        edges[initial].push(("@bol".to_string(), index[&bol]));
     }
 
-    // Mapear cada clase a un nuevo char
-    let mut mapping = HashMap::new();
+    let (edges, finals) = determinize(&reverse(&edges), vec![last], &[initial]);
+    let (edges, finals) = determinize(&reverse(&edges), finals, &[0]);
+
+    // Deshacer la codificación: el estado 0 es el inicio nuevo y el final solo
+    // recibe transiciones @accept/@trail
+    let target_of = |label: &str| edges[0].iter().find(|(l, _)| l == label).map(|(_, to)| *to);
+    let start = target_of("@start");
+    let bol = target_of("@bol");
+    let mut order: Vec<usize> = start.into_iter().chain(bol).collect();
+    let mut state_name: HashMap<usize, char> = HashMap::new();
     let mut next_name = 'A';
-    for block in &P {
-        for &st in block {
-            mapping.insert(st, next_name);
+    let mut i = 0;
+    // Si el inicio normal no tiene futuro igual necesita un nombre, 'A'
+    if start.is_none() {
+        next_name = next_state_name(next_name);
+    }
+    while i < order.len() {
+        let state = order[i];
+        if let Entry::Vacant(entry) = state_name.entry(state) {
+            entry.insert(next_name);
+            next_name = next_state_name(next_name);
+            for (label, to) in &edges[state] {
+                if !label.starts_with('@') {
+                    order.push(*to);
+                }
+            }
         }
-        next_name = ((next_name as u8) + 1) as char;
+        i += 1;
     }
 
-    // Construir DFA minimizado
-    let mut minimized = HashMap::new();
-    let mut minimized_accepts = HashSet::new();
-    for block in &P {
-        let repr = *block.iter().next().unwrap();
-        let new_state = mapping[&repr];
+    let mut minimized = Dfa::new('A', original.token_list.clone());
+    if start.is_none() {
+        minimized.transitions.insert('A', HashMap::new());
+    }
+    for (&state, &name) in &state_name {
         let mut row = HashMap::new();
-        for sym in &alphabet {
-            let target = complete
-                .get(&repr)
-                .and_then(|m| m.get(sym))
-                .copied()
-                .unwrap_or(sink);
-            row.insert(sym.clone(), mapping[&target]);
+        for (label, to) in &edges[state] {
+            if let Some(token) = label.strip_prefix("@accept:") {
+                minimized.accept.insert(name, token.to_string());
+            } else if let Some(token) = label.strip_prefix("@trail:") {
+                minimized.trail.entry(name).or_default().insert(token.to_string());
+            } else if !finals.contains(to) {
+                row.insert(label.clone(), state_name[to]);
+            }
         }
-        minimized.insert(new_state, row);
-        if block.iter().any(|s| accept_states.contains(s)) {
-            minimized_accepts.insert(new_state);
+        minimized.transitions.insert(name, row);
+    }
+    minimized.bol_start = bol.map(|b| state_name[&b]);
+    minimized
+}
+
+/// Verifica que ningún par de estados sea equivalente (refinamiento de Moore).
+/// Un estado inicial sin futuro puede ser equivalente al sink, nada más.
+pub fn verify_minimal(dfa: &Dfa) -> bool {
+    let mut states: Vec<char> = dfa.transitions.keys().cloned().collect();
+    states.sort();
+    let mut alphabet: Vec<&String> = dfa.transitions.values().flat_map(|row| row.keys()).collect();
+    alphabet.sort();
+    alphabet.dedup();
+
+    // None es el sink: sin atributos y sin transiciones
+    let mut block: HashMap<Option<char>, usize> = HashMap::new();
+    let mut count = 0;
+    loop {
+        let mut signatures: HashMap<Vec<Option<usize>>, usize> = HashMap::new();
+        let mut next_block = HashMap::new();
+        for state in states.iter().map(|&s| Some(s)).chain([None]) {
+            let mut signature: Vec<Option<usize>> = Vec::new();
+            let accept = state.and_then(|s| dfa.accept.get(&s));
+            let position = accept.and_then(|t| dfa.token_list.iter().position(|x| x == t));
+            signature.push(accept.map(|_| position.unwrap_or(usize::MAX)));
+            let mut trail: Vec<Option<usize>> = state
+                .and_then(|s| dfa.trail.get(&s))
+                .map(|t| t.iter().map(|x| dfa.token_list.iter().position(|y| y == x)).collect())
+                .unwrap_or_default();
+            trail.sort();
+            signature.push(Some(trail.len()));
+            signature.extend(trail);
+            for sym in &alphabet {
+                let dest = state.and_then(|s| dfa.step_key(s, sym));
+                signature.push(block.get(&dest).copied());
+            }
+            let next = signatures.len();
+            let id = *signatures.entry(signature).or_insert(next);
+            next_block.insert(state, id);
         }
+        let new_count = signatures.len();
+        block = next_block;
+        if new_count == count {
+            break;
+        }
+        count = new_count;
     }
 
-    // Eliminar sink
-    let sink_name = mapping[&sink];
-    minimized.remove(&sink_name);
-    minimized_accepts.remove(&sink_name);
+    let sink_block = block[&None];
+    let mut seen: HashMap<usize, char> = HashMap::new();
+    for &state in &states {
+        let b = block[&Some(state)];
+        if b == sink_block && state != dfa.start && Some(state) != dfa.bol_start {
+            return false;
+        }
+        if seen.insert(b, state).is_some() {
+            return false;
+        }
+    }
+    true
+}
 
-    // Nuevo estado inicial = mapping de 'A'
-    let minimized_start = mapping[&'A'];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::isomorphic;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::regex_parser::parse_rule;
 
-    // println!("Mapa de estados: {:?}", minimized);
-    (minimized, minimized_accepts, minimized_start)
+    #[test]
+    fn test_minimize_merges_equivalent_states() {
+        // (a|b)c y ac|bc llevan al mismo DFA mínimo de 3 estados
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(1, parse_rule("ac|bc"));
+        let dfa = minimize_dfa(&builder.build());
+        assert_eq!(dfa.start, 'A');
+        assert_eq!(dfa.transitions.len(), 3);
+        assert_eq!(dfa.accept.len(), 1);
+        assert!(verify_minimal(&dfa));
+    }
+
+    #[test]
+    fn test_minimize_uses_own_start_and_prunes() {
+        // X -a-> Y (acepta), X -b-> D (muerto), U inalcanzable
+        let mut dfa = Dfa::new('X', vec!["1".to_string()]);
+        dfa.transitions.insert('X', HashMap::from([("a".to_string(), 'Y'), ("b".to_string(), 'D')]));
+        dfa.transitions.insert('Y', HashMap::new());
+        dfa.transitions.insert('D', HashMap::from([("b".to_string(), 'D')]));
+        dfa.transitions.insert('U', HashMap::from([("a".to_string(), 'Y')]));
+        dfa.accept.insert('Y', "1".to_string());
+        dfa.accept.insert('U', "1".to_string());
+        let minimized = minimize_dfa(&dfa);
+        assert_eq!(minimized.start, 'A');
+        assert_eq!(minimized.transitions.len(), 2);
+        assert_eq!(minimized.transitions[&'A'], HashMap::from([("a".to_string(), 'B')]));
+        assert!(verify_minimal(&minimized));
+    }
+
+    #[test]
+    fn test_brzozowski_matches_hopcroft() {
+        let mut builder = LexerBuilder::new();
+        for (index, pattern) in ["ac|bc", "[a-z]+", "[0-9]+/\\.\\.", "\\.\\.", "^#[a-z]+"]
+            .iter()
+            .enumerate()
+        {
+            builder.add_rule(index, parse_rule(pattern));
+        }
+        let dfa = builder.build();
+        let hopcroft = minimize_dfa(&dfa);
+        let brzozowski = minimize_brzozowski(&dfa);
+        assert!(verify_minimal(&brzozowski));
+        assert!(isomorphic(&hopcroft, &brzozowski));
+    }
 }
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::alphabet::{class_key, key_range, split_classes};
+use crate::dfa::Dfa;
+use crate::utilities::next_state_name;
+
+// Operaciones de lenguaje sobre DFAs ya construidos, por construcción del
+// producto. Cada lado se completa implícitamente con un sink (None).
+// El resultado acepta con el token del lado izquierdo si lo tiene, si no con el
+// del derecho. Las marcas de r/s no se conservan: son operaciones de lenguaje.
+
+type Pair = (Option<char>, Option<char>);
+
+impl Dfa {
+    pub fn intersection(&self, other: &Dfa) -> Dfa {
+        product(self, other, false, |l, r| l && r, None)
+    }
+
+    pub fn union(&self, other: &Dfa) -> Dfa {
+        product(self, other, false, |l, r| l || r, None)
+    }
+
+    pub fn difference(&self, other: &Dfa) -> Dfa {
+        product(self, other, false, |l, r| l && !r, None)
+    }
+
+    /// Todas las cadenas que este DFA no acepta, sobre todo el rango de chars.
+    /// Como no tienen token propio, se aceptan con `token`.
+    pub fn complement(&self, token: &str) -> Dfa {
+        let nothing = Dfa::new('A', Vec::new());
+        product(self, &nothing, true, |l, _| !l, Some(token))
+    }
+
+    /// true si el DFA no acepta ninguna cadena
+    pub fn is_empty(&self) -> bool {
+        self.prune().accept.is_empty()
+    }
+}
+
+fn product(
+    a: &Dfa,
+    b: &Dfa,
+    full_alphabet: bool,
+    keep: fn(bool, bool) -> bool,
+    token: Option<&str>,
+) -> Dfa {
+    let mut ranges: Vec<(char, char)> = a
+        .transitions
+        .values()
+        .chain(b.transitions.values())
+        .flat_map(|row| row.keys().map(|key| key_range(key)))
+        .collect();
+    // El complemento también acepta caracteres que no aparecen en el DFA
+    if full_alphabet {
+        ranges.push(('\0', char::MAX));
+    }
+    let mut classes = split_classes(&ranges);
+    classes.sort();
+
+    let mut token_list = a.token_list.clone();
+    for t in b.token_list.iter().map(|t| t.as_str()).chain(token) {
+        if !token_list.iter().any(|x| x == t) {
+            token_list.push(t.to_string());
+        }
+    }
+    let mut dfa = Dfa::new('A', token_list);
+    let mut names: HashMap<Pair, char> = HashMap::new();
+    let mut pending: VecDeque<Pair> = VecDeque::new();
+    let mut state_letter = 'A';
+
+    let start = (Some(a.start), Some(b.start));
+    names.insert(start, state_letter);
+    pending.push_back(start);
+    if a.bol_start.is_some() || b.bol_start.is_some() {
+        let bol = (a.bol_start.or(Some(a.start)), b.bol_start.or(Some(b.start)));
+        let bol_name = *names.entry(bol).or_insert_with(|| {
+            state_letter = next_state_name(state_letter);
+            pending.push_back(bol);
+            state_letter
+        });
+        dfa.bol_start = Some(bol_name);
+    }
+
+    while let Some(pair) = pending.pop_front() {
+        let name = names[&pair];
+        let left = pair.0.and_then(|s| a.accept.get(&s));
+        let right = pair.1.and_then(|s| b.accept.get(&s));
+        if keep(left.is_some(), right.is_some()) {
+            let winner = token.map(|t| t.to_string()).or(left.or(right).cloned());
+            if let Some(winner) = winner {
+                dfa.accept.insert(name, winner);
+            }
+        }
+
+        let mut row = HashMap::new();
+        for &(start, end) in &classes {
+            let next = (
+                pair.0.and_then(|s| a.step(s, start)),
+                pair.1.and_then(|s| b.step(s, start)),
+            );
+            // Los dos en el sink solo importa si el sink acepta (complemento)
+            if next == (None, None) && !keep(false, false) {
+                continue;
+            }
+            let dest = *names.entry(next).or_insert_with(|| {
+                state_letter = next_state_name(state_letter);
+                pending.push_back(next);
+                state_letter
+            });
+            row.insert(class_key(start, end), dest);
+        }
+        dfa.transitions.insert(name, row);
+    }
+    dfa.prune()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dfa::Dfa;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+    use crate::token_identifier::asignar_token;
+
+    fn pattern(id: usize, pattern: &str) -> Dfa {
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(id, parse_rule(pattern));
+        minimize_dfa(&builder.build())
+    }
+
+    #[test]
+    fn test_language_operations() {
+        let ident = pattern(1, "[a-z]+");
+        let keyword = pattern(2, "if|while");
+        assert!(!ident.intersection(&keyword).is_empty());
+        assert!(pattern(3, "[0-9]+").intersection(&keyword).is_empty());
+
+        let not_keyword = ident.difference(&keyword);
+        assert_eq!(asignar_token(&not_keyword, "iff", false), "1");
+        assert_eq!(asignar_token(&not_keyword, "if", false), "UNKNOWN");
+
+        let either = keyword.union(&pattern(3, "[0-9]+"));
+        assert_eq!(asignar_token(&either, "while", false), "2");
+        assert_eq!(asignar_token(&either, "42", false), "3");
+
+        // Cualquier cadena menos las que contienen */
+        let not_close = pattern(4, "(\\*|\\/|[a-z])*\\*\\/(\\*|\\/|[a-z])*").complement("4");
+        assert_eq!(asignar_token(&not_close, "", false), "4");
+        assert_eq!(asignar_token(&not_close, "a*b/ c", false), "4");
+        assert_eq!(asignar_token(&not_close, "a*/b", false), "UNKNOWN");
+    }
+}
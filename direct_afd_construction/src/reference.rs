@@ -0,0 +1,196 @@
+use crate::generator::Rng;
+use crate::grammar_tree::TreeNode;
+use crate::inf_to_pos::Token;
+use crate::lexer_builder::{Construction, LexerBuilder};
+use crate::minimize::minimize_dfa;
+use crate::regex_parser::parse_rule;
+use crate::token_identifier::asignar_token;
+
+// Matcher de referencia: backtracking directo sobre el árbol, sin autómatas.
+// Es lento pero fácil de revisar, y sirve para comparar contra los DFAs de cada
+// construcción antes y después de minimizar.
+
+// Llama a `k` con cada posición en la que puede terminar `node` empezando en
+// `pos`; corta en cuanto `k` devuelve true.
+fn match_from(
+    node: &TreeNode,
+    input: &[char],
+    pos: usize,
+    inicio_de_linea: bool,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    let left = || node.get_left().unwrap();
+    let right = || node.get_right().unwrap();
+    match node.get_value() {
+        Token::Literal(c) => pos < input.len() && input[pos] == *c && k(pos + 1),
+        Token::Range(start, end) => {
+            pos < input.len() && *start <= input[pos] && input[pos] <= *end && k(pos + 1)
+        }
+        // Los marcadores no consumen; ^ solo vale al inicio de una línea
+        Token::Bol => pos == 0 && inicio_de_linea && k(pos),
+        Token::Empty | Token::Trail(_) | Token::Tokener(_) | Token::Sentinel => k(pos),
+        Token::Concat => match_from(&left(), input, pos, inicio_de_linea, &mut |mid| {
+            match_from(&right(), input, mid, inicio_de_linea, k)
+        }),
+        Token::Union => {
+            match_from(&left(), input, pos, inicio_de_linea, k)
+                || match_from(&right(), input, pos, inicio_de_linea, k)
+        }
+        Token::Optional => k(pos) || match_from(&left(), input, pos, inicio_de_linea, k),
+        Token::Kleene => k(pos) || star(&left(), input, pos, inicio_de_linea, k),
+        Token::Plus => star(&left(), input, pos, inicio_de_linea, k),
+        // & y ~ comparan el mismo tramo de la entrada con los dos lados
+        Token::And => (pos..=input.len()).any(|end| {
+            full_match(&left(), &input[..end], pos, inicio_de_linea)
+                && full_match(&right(), &input[..end], pos, inicio_de_linea)
+                && k(end)
+        }),
+        Token::Not => (pos..=input.len()).any(|end| {
+            !full_match(&left(), &input[..end], pos, inicio_de_linea) && k(end)
+        }),
+        other => panic!("Unexpected token {:?} in rule tree", other),
+    }
+}
+
+// Una o más repeticiones; cada vuelta tiene que avanzar para no ciclar
+fn star(
+    body: &TreeNode,
+    input: &[char],
+    pos: usize,
+    inicio_de_linea: bool,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match_from(body, input, pos, inicio_de_linea, &mut |next| {
+        next > pos && (k(next) || star(body, input, next, inicio_de_linea, k))
+    }) || match_from(body, input, pos, inicio_de_linea, &mut |next| next == pos && k(next))
+}
+
+fn full_match(node: &TreeNode, input: &[char], pos: usize, inicio_de_linea: bool) -> bool {
+    match_from(node, input, pos, inicio_de_linea, &mut |end| end == input.len())
+}
+
+/// true si la regla acepta toda la entrada
+pub fn matches(rule: &TreeNode, input: &str, inicio_de_linea: bool) -> bool {
+    let chars: Vec<char> = input.chars().collect();
+    full_match(rule, &chars, 0, inicio_de_linea)
+}
+
+/// Token que debería asignar el lexer: la primera regla que acepta la entrada.
+pub fn expected_token(rules: &[(usize, TreeNode)], input: &str, inicio_de_linea: bool) -> String {
+    rules
+        .iter()
+        .find(|(_, rule)| matches(rule, input, inicio_de_linea))
+        .map_or("UNKNOWN".to_string(), |(index, _)| index.to_string())
+}
+
+fn random_regex(rng: &mut Rng, depth: usize) -> String {
+    const ATOMS: [&str; 6] = ["a", "b", "c", "[a-b]", "[b-d]", "_"];
+    if depth == 0 || rng.below(3) == 0 {
+        return ATOMS[rng.below(ATOMS.len())].to_string();
+    }
+    match rng.below(8) {
+        0 | 1 => format!("{}{}", random_regex(rng, depth - 1), random_regex(rng, depth - 1)),
+        2 => format!("({}|{})", random_regex(rng, depth - 1), random_regex(rng, depth - 1)),
+        3 => format!("({})*", random_regex(rng, depth - 1)),
+        4 => format!("({})+", random_regex(rng, depth - 1)),
+        5 => format!("({})?", random_regex(rng, depth - 1)),
+        6 => format!("({}&{})", random_regex(rng, depth - 1), random_regex(rng, depth - 1)),
+        _ => format!("(~({}))", random_regex(rng, depth - 1)),
+    }
+}
+
+// Regla al azar, a veces anclada con ^ o con contexto posterior
+fn random_rule(rng: &mut Rng) -> String {
+    let mut rule = random_regex(rng, 3);
+    if rng.below(6) == 0 {
+        rule = format!("^{}", rule);
+    }
+    if rng.below(6) == 0 {
+        rule = format!("{}/{}", rule, random_regex(rng, 1));
+    }
+    rule
+}
+
+fn random_input(rng: &mut Rng) -> String {
+    const CHARS: [char; 5] = ['a', 'b', 'c', 'd', 'e'];
+    (0..rng.below(7)).map(|_| CHARS[rng.below(CHARS.len())]).collect()
+}
+
+// Una regla que no acepta nada no se puede compilar con & y ~ reescritos
+fn is_empty_rule(rule: &TreeNode) -> bool {
+    let mut builder = LexerBuilder::new();
+    builder.allow_empty(true).construction(Construction::Derivative);
+    builder.add_rule(0, rule.clone());
+    builder.build().is_empty()
+}
+
+/// Compara el matcher de referencia con los DFAs de cada construcción, sin
+/// minimizar y minimizados, sobre `cases` lexers y entradas al azar. Devuelve
+/// cuántas entradas se compararon o el primer caso en el que no coinciden.
+pub fn differential(seed: u64, cases: usize) -> Result<usize, String> {
+    let mut rng = Rng::new(seed);
+    let mut compared = 0;
+    for _ in 0..cases {
+        let mut patterns = Vec::new();
+        let mut rules = Vec::new();
+        while patterns.len() < 1 + rng.below(3) {
+            let pattern = random_rule(&mut rng);
+            let rule = parse_rule(&pattern);
+            if !is_empty_rule(&rule) {
+                rules.push((patterns.len() + 1, rule));
+                patterns.push(pattern);
+            }
+        }
+        let inputs: Vec<String> = (0..30).map(|_| random_input(&mut rng)).collect();
+
+        for construction in [Construction::Direct, Construction::Thompson, Construction::Derivative] {
+            let mut builder = LexerBuilder::new();
+            builder.allow_empty(true).construction(construction);
+            for (index, rule) in &rules {
+                builder.add_rule(*index, rule.clone());
+            }
+            let dfa = builder.build();
+            let minimized = minimize_dfa(&dfa);
+            for input in &inputs {
+                for inicio_de_linea in [false, true] {
+                    let expected = expected_token(&rules, input, inicio_de_linea);
+                    let got = asignar_token(&dfa, input, inicio_de_linea);
+                    let got_min = asignar_token(&minimized, input, inicio_de_linea);
+                    if got != expected || got_min != expected {
+                        return Err(format!(
+                            "{:?} {:?} on {:?}{}: expected {}, DFA {}, minimized {}",
+                            construction,
+                            patterns,
+                            input,
+                            if inicio_de_linea { " (at line start)" } else { "" },
+                            expected,
+                            got,
+                            got_min
+                        ));
+                    }
+                    compared += 1;
+                }
+            }
+        }
+    }
+    Ok(compared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_matcher_agrees_with_dfas() {
+        let rule = parse_rule("(a|ab)(c|bcd)(d*)");
+        assert!(matches(&rule, "abcd", false));
+        assert!(matches(&rule, "abcdd", false));
+        assert!(!matches(&rule, "abd", false));
+        assert!(matches(&parse_rule("[a-z]+&~(if)"), "iff", false));
+        assert!(!matches(&parse_rule("^#"), "#", false));
+
+        if let Err(case) = differential(1, 150) {
+            panic!("{}", case);
+        }
+    }
+}
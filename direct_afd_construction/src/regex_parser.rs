@@ -0,0 +1,276 @@
+use std::rc::Rc;
+
+use crate::grammar_tree::{Tree, TreeNode};
+use crate::inf_to_pos::Token;
+
+// Parser descendente recursivo: del patrón al árbol sintáctico sin pasar por postfix.
+//
+// trail   := union ('/' union)?
+// union   := inter ('|' inter)*
+// inter   := concat ('&' concat)*
+// concat  := '^'? unary+
+// unary   := '~' unary | postfix
+// postfix := atom ('*' | '+' | '?')*
+// atom    := '(' union ')' | '[' c '-' c ']' | '{' id '}' | '_' | '\' c | c
+//
+// El contexto posterior `/` solo va en el nivel superior de la regla: dentro de
+// un grupo el corte del lexema no tendría un lugar fijo.
+//
+// `_` es cualquier caracter; `&` y `~` se compilan por producto de DFAs (ver extended).
+//
+// Los {id} solo se aceptan en `parse`; en `parse_rule` las llaves son literales
+// porque el marcador de cada regla lo agrega el LexerBuilder.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // Solo la regex combinada de depuración trae marcadores {id}
+    allow_tokener: bool,
+}
+
+fn leaf(value: Token) -> TreeNode {
+    TreeNode::new(value, None, None)
+}
+
+fn binary(value: Token, left: TreeNode, right: TreeNode) -> TreeNode {
+    TreeNode::new(value, Some(Rc::new(left)), Some(Rc::new(right)))
+}
+
+// a∘b∘c se agrupa como a∘(b∘c), igual que en la forma postfix de inf_to_pos
+fn right_fold(value: Token, items: Vec<TreeNode>) -> TreeNode {
+    items
+        .into_iter()
+        .rev()
+        .reduce(|acc, item| binary(value.clone(), item, acc))
+        .unwrap()
+}
+
+fn check_range(start: char, end: char) -> bool {
+    if start > end {
+        return false;
+    }
+    let both_char = start.is_alphabetic() && end.is_alphabetic();
+    let both_num = start.is_numeric() && end.is_numeric();
+    both_char || both_num
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn error(&self, msg: &str) -> ! {
+        let pattern: String = self.chars.iter().collect();
+        panic!("Invalid regex {:?} at position {}: {}", pattern, self.pos, msg);
+    }
+
+    fn parse_trail(&mut self) -> TreeNode {
+        let head = self.parse_union();
+        if self.peek() != Some('/') {
+            return head;
+        }
+        self.next();
+        let tail = self.parse_union();
+        if self.peek() == Some('/') {
+            self.error("only one trailing context '/' is allowed");
+        }
+        // r/s se guarda como (r ∘ marcador) ∘ s, el marcador es una posición más
+        let marker = leaf(Token::Trail(String::new()));
+        binary(Token::Concat, binary(Token::Concat, head, marker), tail)
+    }
+
+    fn parse_union(&mut self) -> TreeNode {
+        let mut options = vec![self.parse_inter()];
+        while self.peek() == Some('|') {
+            self.next();
+            options.push(self.parse_inter());
+        }
+        right_fold(Token::Union, options)
+    }
+
+    fn parse_inter(&mut self) -> TreeNode {
+        let mut items = vec![self.parse_concat()];
+        while self.peek() == Some('&') {
+            self.next();
+            items.push(self.parse_concat());
+        }
+        right_fold(Token::And, items)
+    }
+
+    fn parse_concat(&mut self) -> TreeNode {
+        let mut items: Vec<TreeNode> = Vec::new();
+        if self.peek() == Some('^') {
+            self.next();
+            items.push(leaf(Token::Bol));
+        }
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' || c == '/' || c == '&' {
+                break;
+            }
+            let item = self.parse_unary();
+            if let Token::Tokener(id) = item.get_value() {
+                items = items.iter().map(|prev| prev.with_trail_owner(id)).collect();
+            }
+            items.push(item);
+        }
+        if items.is_empty() || items.iter().all(|n| *n.get_value() == Token::Bol) {
+            self.error("expected an expression");
+        }
+        right_fold(Token::Concat, items)
+    }
+
+    fn parse_unary(&mut self) -> TreeNode {
+        if self.peek() == Some('~') {
+            self.next();
+            let inner = self.parse_unary();
+            return TreeNode::new(Token::Not, Some(Rc::new(inner)), None);
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> TreeNode {
+        let mut node = self.parse_atom();
+        while let Some(c) = self.peek() {
+            let op = match c {
+                '*' => Token::Kleene,
+                '+' => Token::Plus,
+                '?' => Token::Optional,
+                _ => break,
+            };
+            self.next();
+            node = TreeNode::new(op, Some(Rc::new(node)), None);
+        }
+        node
+    }
+
+    fn parse_atom(&mut self) -> TreeNode {
+        match self.next() {
+            Some('(') => {
+                let inner = self.parse_union();
+                if self.peek() == Some('/') {
+                    self.error("trailing context '/' is only allowed at the top level of a rule");
+                }
+                if self.next() != Some(')') {
+                    self.error("expected ')'");
+                }
+                inner
+            }
+            Some('[') => {
+                match (self.next(), self.next(), self.next(), self.next()) {
+                    (Some(start), Some('-'), Some(end), Some(']')) => {
+                        if !check_range(start, end) {
+                            self.error("invalid range, start bigger than end");
+                        }
+                        leaf(Token::Range(start, end))
+                    }
+                    _ => self.error("invalid range syntax, expected [a-z]"),
+                }
+            }
+            Some('{') if self.allow_tokener => {
+                let mut id = String::new();
+                loop {
+                    match self.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_alphanumeric() || c == '_' => id.push(c),
+                        _ => self.error("invalid token name, expected {name}"),
+                    }
+                }
+                if id.is_empty() {
+                    self.error("expected a token name between braces");
+                }
+                leaf(Token::Tokener(id))
+            }
+            Some('\\') => match self.next() {
+                Some('n') => leaf(Token::Literal('\n')),
+                Some('t') => leaf(Token::Literal('\t')),
+                Some('r') => leaf(Token::Literal('\r')),
+                Some(c) => leaf(Token::Literal(c)),
+                None => self.error("dangling escape"),
+            },
+            Some('_') => leaf(Token::Range('\0', char::MAX)),
+            Some(c) if "*+?)|]&".contains(c) || (c == '}' && self.allow_tokener) => {
+                self.pos -= 1;
+                self.error(&format!("unexpected '{}'", c))
+            }
+            Some(c) => leaf(Token::Literal(c)),
+            None => self.error("unexpected end of pattern"),
+        }
+    }
+}
+
+fn parse_pattern(pattern: &str, allow_tokener: bool) -> TreeNode {
+    let mut parser = Parser {
+        chars: pattern.chars().collect(),
+        pos: 0,
+        allow_tokener,
+    };
+    let root = parser.parse_trail();
+    if parser.pos < parser.chars.len() {
+        parser.error("unbalanced ')'");
+    }
+    root
+}
+
+/// Árbol de una sola regla del spec, sin marcador de token ni `#` final.
+pub fn parse_rule(pattern: &str) -> TreeNode {
+    parse_pattern(pattern, false)
+}
+
+/// Convierte un patrón en su árbol sintáctico, ya concatenado con el `#` final.
+/// Entra en pánico con la posición del error si el patrón está mal formado.
+pub fn parse(pattern: &str) -> Tree {
+    let root = parse_pattern(pattern, true);
+    Tree::from_root(binary(Token::Concat, root, leaf(Token::Sentinel)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inf_to_pos::inf_to_pos;
+
+    fn postfix(node: &TreeNode, out: &mut Vec<Token>) {
+        if let Some(left) = node.get_left() {
+            postfix(&left, out);
+        }
+        if let Some(right) = node.get_right() {
+            postfix(&right, out);
+        }
+        out.push(node.get_value().clone());
+    }
+
+    #[test]
+    fn test_parse_matches_postfix() {
+        for pattern in [
+            r"(A[3-9]|((s!)*))\~t",
+            r"(ab)+c?",
+            r"((if){IF})|([a-z]+{ID})|((ab|d){TEST})",
+            r"(-?)[0-9]+\.[0-9]*",
+        ] {
+            let mut tokens = Vec::new();
+            postfix(&parse(pattern).get_root().unwrap(), &mut tokens);
+            assert_eq!(tokens, inf_to_pos(pattern), "{}", pattern);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "expected ')'")]
+    fn test_parse_rejects_unbalanced() {
+        parse("(ab|c");
+    }
+
+    #[test]
+    fn test_trailing_context_only_at_top_level() {
+        for pattern in ["(a/b)*", "(a/b)c", "x(a|b/c)"] {
+            let result = std::panic::catch_unwind(|| parse_rule(pattern));
+            let message = *result.expect_err(pattern).downcast::<String>().unwrap();
+            assert!(message.contains("only allowed at the top level"), "{}", message);
+        }
+        // Los grupos sí pueden ir a cada lado del /
+        parse_rule("(a|b)+/(c)");
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use petgraph::algo::{dijkstra, is_cyclic_directed, toposort};
+use petgraph::graph::NodeIndex;
+use petgraph::Graph;
+
+use crate::alphabet::key_range;
+use crate::dfa::Dfa;
+use crate::equivalence::shortest_match;
+use crate::grammar_tree::TreeNode;
+use crate::inf_to_pos::Token;
+use crate::lexer_builder::LexerBuilder;
+use crate::minimize::minimize_dfa;
+use crate::token_identifier::asignar_token;
+use crate::view::{generate_graph, get_all_states};
+
+// Análisis de las reglas del spec sobre el DFA combinado y los DFAs de cada
+// regla por separado. Las reglas se identifican por su línea en el .yal, que es
+// también su id de token.
+
+/// Regla que nunca es el token ganador, con las reglas anteriores que la tapan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadowed {
+    pub line: usize,
+    pub by: Vec<usize>,
+}
+
+/// Par de reglas que aceptan un mismo lexema, con el más corto y la regla que
+/// elige el scanner (puede ser una tercera con más prioridad que las dos).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ambiguity {
+    pub first: usize,
+    pub second: usize,
+    pub lexeme: String,
+    pub inicio_de_linea: bool,
+    pub winner: String,
+}
+
+/// Lenguaje de una regla: longitudes de lexema (None si no tiene cota) y los
+/// ejemplos más cortos. Con contexto posterior se cuenta también la parte s,
+/// que es lo que el scanner tiene que leer antes de decidir.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleStats {
+    pub line: usize,
+    pub finite: bool,
+    pub min_len: usize,
+    pub max_len: Option<usize>,
+    pub examples: Vec<String>,
+}
+
+pub fn rule_dfa(line: usize, rule: &TreeNode) -> Dfa {
+    let mut builder = LexerBuilder::new();
+    builder.allow_empty(true);
+    builder.add_rule(line, rule.clone());
+    minimize_dfa(&builder.build())
+}
+
+fn rule_languages(rules: &[(usize, TreeNode)]) -> Vec<Dfa> {
+    rules
+        .iter()
+        .map(|(line, rule)| rule_dfa(*line, rule))
+        .collect()
+}
+
+/// Reglas que no ganan en ningún estado de aceptación del DFA combinado.
+/// `by` son las reglas con más prioridad que aceptan alguna cadena en común.
+pub fn find_shadowed(rules: &[(usize, TreeNode)], dfa: &Dfa) -> Vec<Shadowed> {
+    let winners: HashSet<&String> = dfa.accept.values().collect();
+    let languages = rule_languages(rules);
+    let mut shadowed = Vec::new();
+    for (i, (line, _rule)) in rules.iter().enumerate() {
+        if winners.contains(&line.to_string()) {
+            continue;
+        }
+        let by = (0..i)
+            .filter(|&j| !languages[i].intersection(&languages[j]).is_empty())
+            .map(|j| rules[j].0)
+            .collect();
+        shadowed.push(Shadowed { line: *line, by });
+    }
+    shadowed
+}
+
+// r en r/s, que es lo que la regla consume; s solo decide si aplica. El
+// parser deja el contexto posterior en la raíz: (r ∘ marcador) ∘ s
+fn lexeme_part(rule: &TreeNode) -> TreeNode {
+    if let (Token::Concat, Some(left)) = (rule.get_value(), rule.get_left()) {
+        let marker = left.get_right();
+        if matches!(marker.as_deref().map(|m| m.get_value()), Some(Token::Trail(_))) {
+            return left.get_left().unwrap().as_ref().clone();
+        }
+    }
+    rule.clone()
+}
+
+/// Todos los pares de reglas cuyos lexemas se cruzan, en orden de prioridad.
+/// De una regla r/s se compara solo r: "1" en [0-9]+/".." y [0-9]+ es ambiguo
+/// aunque "1.." no lo acepte [0-9]+.
+pub fn find_ambiguities(rules: &[(usize, TreeNode)], dfa: &Dfa) -> Vec<Ambiguity> {
+    let lexemes: Vec<(usize, TreeNode)> = rules
+        .iter()
+        .map(|(line, rule)| (*line, lexeme_part(rule)))
+        .collect();
+    let languages = rule_languages(&lexemes);
+    let mut ambiguities = Vec::new();
+    for i in 0..rules.len() {
+        for j in i + 1..rules.len() {
+            let Some(shared) = shortest_match(&languages[i].intersection(&languages[j])) else {
+                continue;
+            };
+            let winner = asignar_token(dfa, &shared.input, shared.inicio_de_linea);
+            ambiguities.push(Ambiguity {
+                first: rules[i].0,
+                second: rules[j].0,
+                lexeme: shared.input,
+                inicio_de_linea: shared.inicio_de_linea,
+                winner,
+            });
+        }
+    }
+    ambiguities
+}
+
+// Caracter con el que se muestra una clase: uno visible si la clase lo tiene
+fn representative(key: &str) -> char {
+    let (start, end) = key_range(key);
+    if start <= '~' && end >= '!' {
+        start.max('!')
+    } else {
+        start
+    }
+}
+
+fn starts(dfa: &Dfa) -> Vec<char> {
+    let mut starts = vec![dfa.start];
+    starts.extend(dfa.bol_start.filter(|&bol| bol != dfa.start));
+    starts
+}
+
+// Las `count` cadenas más cortas, en orden de longitud y luego alfabético
+fn shortest_examples(dfa: &Dfa, count: usize) -> Vec<String> {
+    let mut examples: Vec<String> = Vec::new();
+    for start in starts(dfa) {
+        let mut pendientes = VecDeque::from([(start, String::new())]);
+        let mut found = 0;
+        while let Some((state, prefix)) = pendientes.pop_front() {
+            if found == count {
+                break;
+            }
+            if dfa.accept.contains_key(&state) {
+                examples.push(prefix.clone());
+                found += 1;
+            }
+            let mut row: Vec<(char, char)> = dfa.transitions[&state]
+                .iter()
+                .map(|(key, &dest)| (representative(key), dest))
+                .collect();
+            row.sort();
+            for (c, dest) in row {
+                pendientes.push_back((dest, format!("{}{}", prefix, c)));
+            }
+        }
+    }
+    examples.sort_by(|a, b| (a.chars().count(), a).cmp(&(b.chars().count(), b)));
+    examples.dedup();
+    examples.truncate(count);
+    examples
+}
+
+/// Analiza el lenguaje de una regla sobre el grafo de su DFA mínimo. Como el
+/// DFA está podado, un ciclo en el grafo es un lenguaje infinito.
+pub fn rule_stats(line: usize, rule: &TreeNode, examples: usize) -> RuleStats {
+    let dfa = rule_dfa(line, rule);
+    let mut states = get_all_states(&dfa.transitions);
+    for start in starts(&dfa) {
+        if !states.contains(&start.to_string()) {
+            states.push(start.to_string());
+        }
+    }
+    let graph: Graph<String, String> = generate_graph(&dfa.transitions, &states);
+    let node = |state: char| -> NodeIndex {
+        graph
+            .node_indices()
+            .find(|&i| graph[i] == state.to_string())
+            .expect("Node not found")
+    };
+    let accepting: HashSet<NodeIndex> = dfa.accept.keys().map(|&s| node(s)).collect();
+    if accepting.is_empty() {
+        return RuleStats {
+            line,
+            finite: true,
+            min_len: 0,
+            max_len: Some(0),
+            examples: Vec::new(),
+        };
+    }
+
+    let min_len = starts(&dfa)
+        .into_iter()
+        .flat_map(|start| {
+            dijkstra(&graph, node(start), None, |_| 1usize)
+                .into_iter()
+                .filter(|(n, _)| accepting.contains(n))
+                .map(|(_, len)| len)
+        })
+        .min()
+        .unwrap();
+
+    let finite = !is_cyclic_directed(&graph);
+    let max_len = if finite {
+        // Camino más largo en el DAG, en orden topológico desde cada inicio
+        let order = toposort(&graph, None).unwrap();
+        starts(&dfa)
+            .into_iter()
+            .flat_map(|start| {
+                let mut longest: HashMap<NodeIndex, usize> = HashMap::from([(node(start), 0)]);
+                for &n in &order {
+                    if let Some(&len) = longest.get(&n) {
+                        for next in graph.neighbors(n) {
+                            let entry = longest.entry(next).or_insert(0);
+                            *entry = (*entry).max(len + 1);
+                        }
+                    }
+                }
+                longest
+                    .into_iter()
+                    .filter(|(n, _)| accepting.contains(n))
+                    .map(|(_, len)| len)
+            })
+            .max()
+    } else {
+        None
+    };
+
+    RuleStats {
+        line,
+        finite,
+        min_len,
+        max_len,
+        examples: shortest_examples(&dfa, examples),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::gen_rules;
+    use crate::regex_parser::parse_rule;
+
+    #[test]
+    fn test_keyword_after_identifier_is_shadowed() {
+        let rules: Vec<(usize, TreeNode)> =
+            [(3, "[0-9]+"), (4, "[a-z]+"), (5, "while"), (6, "-?[0-9]+")]
+                .iter()
+                .map(|(line, pattern)| (*line, parse_rule(pattern)))
+                .collect();
+        let mut builder = LexerBuilder::new();
+        for (line, rule) in &rules {
+            builder.add_rule(*line, rule.clone());
+        }
+        let dfa = minimize_dfa(&builder.build());
+        assert_eq!(
+            find_shadowed(&rules, &dfa),
+            vec![Shadowed {
+                line: 5,
+                by: vec![4]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_rule_reports_file_line() {
+        let path = std::env::temp_dir().join("rule_analysis_shadowed.yal");
+        let spec = "{\n}\nrule actions =\n    [a-z]+     { tk_list.push(\"ID\"); }\n    | \"while\"  { tk_list.push(\"WHILE\"); }\n";
+        std::fs::write(&path, spec).unwrap();
+        let (rules, _actions, _eof_token) = gen_rules(path.to_str().unwrap());
+        let mut builder = LexerBuilder::new();
+        for (line, rule) in &rules {
+            builder.add_rule(*line, rule.clone());
+        }
+        let dfa = minimize_dfa(&builder.build());
+        // "while" está en la línea 5 del archivo y [a-z]+ en la 4
+        assert_eq!(
+            find_shadowed(&rules, &dfa),
+            vec![Shadowed {
+                line: 5,
+                by: vec![4]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ambiguity_report() {
+        let rules: Vec<(usize, TreeNode)> =
+            [(1, "if"), (2, "[a-z]+"), (3, "[0-9]+"), (4, "[a-z][0-9]")]
+                .iter()
+                .map(|(line, pattern)| (*line, parse_rule(pattern)))
+                .collect();
+        let mut builder = LexerBuilder::new();
+        for (line, rule) in &rules {
+            builder.add_rule(*line, rule.clone());
+        }
+        let dfa = minimize_dfa(&builder.build());
+        let report = find_ambiguities(&rules, &dfa);
+        assert_eq!(report.len(), 1);
+        assert_eq!((report[0].first, report[0].second), (1, 2));
+        assert_eq!(report[0].lexeme, "if");
+        assert_eq!(report[0].winner, "1");
+
+        // En test.yal "while" está en la línea 8 y [a-z]+ en la 9
+        let (rules, _actions, _eof_token) = gen_rules("./test.yal");
+        let mut builder = LexerBuilder::new();
+        for (line, rule) in &rules {
+            builder.add_rule(*line, rule.clone());
+        }
+        let dfa = minimize_dfa(&builder.build());
+        let report = find_ambiguities(&rules, &dfa);
+        let keyword = report.iter().find(|a| a.lexeme == "while").unwrap();
+        assert_eq!((keyword.first, keyword.second), (8, 9));
+        assert_eq!(keyword.winner, "8");
+        // La parte r de [0-9]+/".." (línea 4) choca con [0-9]+ (línea 7)
+        let int = report.iter().find(|a| (a.first, a.second) == (4, 7)).unwrap();
+        assert_eq!(int.lexeme, "0");
+    }
+
+    #[test]
+    fn test_trailing_context_ambiguity_uses_lexeme() {
+        let rules: Vec<(usize, TreeNode)> = [(1, "[0-9]+/\\.\\."), (2, "[0-9]+"), (3, "a/b"), (4, "ab")]
+            .iter()
+            .map(|(line, pattern)| (*line, parse_rule(pattern)))
+            .collect();
+        let mut builder = LexerBuilder::new();
+        for (line, rule) in &rules {
+            builder.add_rule(*line, rule.clone());
+        }
+        let dfa = minimize_dfa(&builder.build());
+        let report = find_ambiguities(&rules, &dfa);
+        assert_eq!(report.len(), 1);
+        assert_eq!((report[0].first, report[0].second), (1, 2));
+        assert_eq!(report[0].lexeme, "0");
+        assert_eq!(report[0].winner, "2");
+    }
+
+    #[test]
+    fn test_rule_stats() {
+        let keyword = rule_stats(1, &parse_rule("if|else"), 5);
+        assert!(keyword.finite);
+        assert_eq!((keyword.min_len, keyword.max_len), (2, Some(4)));
+        assert_eq!(keyword.examples, vec!["if", "else"]);
+
+        let number = rule_stats(2, &parse_rule("-?[0-9]+"), 3);
+        assert!(!number.finite);
+        assert_eq!((number.min_len, number.max_len), (1, None));
+        assert_eq!(number.examples, vec!["0", "-0", "00"]);
+
+        // Las reglas de un spec llevan su línea en el archivo: "while" es la 8
+        let (rules, _actions, _eof_token) = gen_rules("./test.yal");
+        let (line, rule) = &rules[4];
+        let keyword = rule_stats(*line, rule, 1);
+        assert_eq!(keyword.line, 8);
+        assert_eq!(keyword.examples, vec!["while"]);
+    }
+}
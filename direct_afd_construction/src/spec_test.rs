@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::compile::{gen_rules, token_name};
+use crate::dfa::Dfa;
+use crate::lex_reader::get_inline_tests;
+use crate::utilities::generate;
+
+// Pruebas de un spec: casos en un directorio (`<caso>.in` con la entrada y
+// `<caso>.out` con los tokens esperados) y casos dentro del mismo .yal en un
+// bloque (* test ... *), una línea por caso: "entrada" => TOKEN TOKEN ...
+// En los casos del .yal no se escribe el token de eof.
+
+pub struct SpecCase {
+    pub name: String,
+    pub input: String,
+    pub expected: Vec<String>,
+    pub with_eof: bool,
+}
+
+/// Lexer de un spec ya compilado, emite nombres de token
+pub struct SpecLexer {
+    dfa: Dfa,
+    names: HashMap<String, Option<String>>,
+    eof_token: Option<String>,
+}
+
+impl SpecLexer {
+    pub fn new(spec: &str) -> Self {
+        let (rules, actions, eof_token) = gen_rules(spec);
+        let names = actions
+            .iter()
+            .map(|(line, action)| (line.to_string(), token_name(action)))
+            .collect();
+        Self {
+            dfa: generate(rules),
+            names,
+            eof_token: eof_token.map(|t| t.to_string()),
+        }
+    }
+
+    pub fn tokens(&self, input: &str, with_eof: bool) -> Vec<String> {
+        let eof_token = self.eof_token.clone().filter(|_| with_eof);
+        crate::simulate(input.to_string(), self.dfa.clone(), eof_token)
+            .into_iter()
+            .filter_map(|id| self.names.get(&id).cloned().unwrap_or(Some(id)))
+            .collect()
+    }
+}
+
+fn invalid_inline(line: &str, line_num: usize) -> ! {
+    panic!("Invalid inline test at line {}: {}", line_num, line)
+}
+
+// Entrada entre comillas con \n, \t, \" y \\ como escapes
+fn parse_inline(line: &str, line_num: usize) -> SpecCase {
+    let mut chars = line.chars();
+    if chars.next() != Some('"') {
+        invalid_inline(line, line_num);
+    }
+    let mut input = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+                Some('n') => input.push('\n'),
+                Some('t') => input.push('\t'),
+                Some(c) => input.push(c),
+                None => invalid_inline(line, line_num),
+            },
+            Some(c) => input.push(c),
+            None => invalid_inline(line, line_num),
+        }
+    }
+    let Some(expected) = chars.as_str().trim().strip_prefix("=>") else {
+        invalid_inline(line, line_num)
+    };
+    SpecCase {
+        name: format!("line {}", line_num),
+        input,
+        expected: expected.split_whitespace().map(|t| t.to_string()).collect(),
+        with_eof: false,
+    }
+}
+
+pub fn inline_cases(spec: &str) -> Vec<SpecCase> {
+    get_inline_tests(spec)
+        .iter()
+        .map(|(line, line_num)| parse_inline(line, *line_num))
+        .collect()
+}
+
+/// Casos de un directorio, en orden por nombre
+pub fn directory_cases(dir: &Path) -> Vec<SpecCase> {
+    let mut inputs: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|_| panic!("Cannot read directory {}", dir.display()))
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "in"))
+        .collect();
+    inputs.sort();
+    inputs
+        .into_iter()
+        .map(|input| {
+            let expected = input.with_extension("out");
+            let expected = fs::read_to_string(&expected)
+                .unwrap_or_else(|_| panic!("Missing expected output {}", expected.display()));
+            SpecCase {
+                name: input.file_stem().unwrap().to_string_lossy().to_string(),
+                input: fs::read_to_string(&input).unwrap(),
+                expected: expected.split_whitespace().map(|t| t.to_string()).collect(),
+                with_eof: true,
+            }
+        })
+        .collect()
+}
+
+/// Diferencia por tokens (LCS): "  " iguales, "- " esperados que faltan, "+ "
+/// producidos de más.
+pub fn token_diff(expected: &[String], actual: &[String]) -> Vec<String> {
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            diff.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push(format!("+ {}", actual[j]));
+            j += 1;
+        } else {
+            diff.push(format!("- {}", expected[i]));
+            i += 1;
+        }
+    }
+    diff
+}
+
+/// Corre un caso; Err con el diff (o el panic del lexer) si no coincide
+pub fn run_case(lexer: &SpecLexer, case: &SpecCase) -> Result<(), Vec<String>> {
+    let actual = catch_unwind(AssertUnwindSafe(|| lexer.tokens(&case.input, case.with_eof)))
+        .map_err(|err| {
+            let message = err
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| err.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            vec![format!("lexer panicked: {}", message)]
+        })?;
+    if actual == case.expected {
+        Ok(())
+    } else {
+        Err(token_diff(&case.expected, &actual))
+    }
+}
+
+/// Corre los casos de un directorio (con un solo .yal) o los de un .yal.
+/// Imprime cada fallo con su diff y devuelve (pasados, fallidos).
+pub fn run_tests(path: &str) -> (usize, usize) {
+    let path = Path::new(path);
+    let (spec, mut cases) = if path.is_dir() {
+        let specs: Vec<_> = fs::read_dir(path)
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "yal"))
+            .collect();
+        if specs.len() != 1 {
+            panic!("{} must contain exactly one .yal spec", path.display());
+        }
+        (specs[0].clone(), directory_cases(path))
+    } else {
+        (path.to_path_buf(), Vec::new())
+    };
+    let spec = spec.to_string_lossy().to_string();
+    cases.extend(inline_cases(&spec));
+
+    let lexer = SpecLexer::new(&spec);
+    let (mut passed, mut failed) = (0, 0);
+    for case in &cases {
+        match run_case(&lexer, case) {
+            Ok(()) => passed += 1,
+            Err(diff) => {
+                failed += 1;
+                println!("FAILED {} ({:?})", case.name, case.input);
+                for line in diff {
+                    println!("    {}", line);
+                }
+            }
+        }
+    }
+    println!("{}: {} passed; {} failed", spec, passed, failed);
+    (passed, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_cases() {
+        assert_eq!(token_name("tk_list.push(INT);").as_deref(), Some("INT"));
+        assert_eq!(token_name(" FLOAT ").as_deref(), Some("FLOAT"));
+        assert_eq!(token_name(""), None);
+
+        let expected: Vec<String> = ["ID", "GT", "INT"].iter().map(|t| t.to_string()).collect();
+        let actual: Vec<String> = ["ID", "GTE", "INT"].iter().map(|t| t.to_string()).collect();
+        assert_eq!(token_diff(&expected, &actual), vec!["  ID", "+ GTE", "- GT", "  INT"]);
+
+        let spec = concat!(env!("CARGO_MANIFEST_DIR"), "/test.yal");
+        assert_eq!(run_tests(spec), (inline_cases(spec).len(), 0));
+        assert_eq!(run_tests(concat!(env!("CARGO_MANIFEST_DIR"), "/cases/calc")).1, 0);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::alphabet::{class_key, split_classes};
+use crate::dfa::Dfa;
+use crate::grammar_tree::{Tree, TreeNode};
+use crate::inf_to_pos::Token;
+use crate::utilities::next_state_name;
+
+// Construcción de Thompson sobre el mismo árbol que usa DirectAFD, seguida de
+// la construcción de subconjuntos. Sirve para comparar contra el método directo.
+
+#[derive(Debug, Default, Clone)]
+struct NfaState {
+    epsilon: Vec<usize>,
+    edges: Vec<((char, char), usize)>,
+    // Transición que solo se toma al inicio de línea (^)
+    bol: Vec<usize>,
+    // El estado es el marcador de fin de la regla `token`
+    token: Option<String>,
+    // El estado es el fin de la parte r en r/s de `trail`
+    trail: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Nfa {
+    states: Vec<NfaState>,
+    start: usize,
+    // Tokens en el orden en que aparecen, es su prioridad
+    token_list: Vec<String>,
+}
+
+impl Nfa {
+    pub fn from_tree(tree: &Tree) -> Self {
+        let mut nfa = Nfa::default();
+        let root = tree.get_root().expect("The syntax tree is empty");
+        let (start, _end) = nfa.fragment(&root);
+        nfa.start = start;
+        nfa
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    fn add_epsilon(&mut self, from: usize, to: usize) {
+        self.states[from].epsilon.push(to);
+    }
+
+    // Devuelve (inicio, fin) del fragmento de cada nodo
+    fn fragment(&mut self, node: &TreeNode) -> (usize, usize) {
+        match node.get_value() {
+            Token::Literal(c) => self.edge_fragment(*c, *c),
+            Token::Range(start, end) => self.edge_fragment(*start, *end),
+            Token::Tokener(id) => {
+                let state = self.add_state();
+                self.states[state].token = Some(id.clone());
+                self.token_list.push(id.clone());
+                (state, state)
+            }
+            Token::Trail(id) => {
+                let state = self.add_state();
+                self.states[state].trail = Some(id.clone());
+                (state, state)
+            }
+            Token::Bol => {
+                let start = self.add_state();
+                let end = self.add_state();
+                self.states[start].bol.push(end);
+                (start, end)
+            }
+            Token::Sentinel | Token::Empty => {
+                let state = self.add_state();
+                (state, state)
+            }
+            Token::Concat => {
+                let (l_start, l_end) = self.fragment(&node.get_left().unwrap());
+                let (r_start, r_end) = self.fragment(&node.get_right().unwrap());
+                self.add_epsilon(l_end, r_start);
+                (l_start, r_end)
+            }
+            Token::Union => {
+                let start = self.add_state();
+                let (l_start, l_end) = self.fragment(&node.get_left().unwrap());
+                let (r_start, r_end) = self.fragment(&node.get_right().unwrap());
+                let end = self.add_state();
+                self.add_epsilon(start, l_start);
+                self.add_epsilon(start, r_start);
+                self.add_epsilon(l_end, end);
+                self.add_epsilon(r_end, end);
+                (start, end)
+            }
+            Token::Kleene | Token::Plus | Token::Optional => {
+                let start = self.add_state();
+                let (c_start, c_end) = self.fragment(&node.get_left().unwrap());
+                let end = self.add_state();
+                self.add_epsilon(start, c_start);
+                self.add_epsilon(c_end, end);
+                if *node.get_value() != Token::Plus {
+                    self.add_epsilon(start, end);
+                }
+                if *node.get_value() != Token::Optional {
+                    self.add_epsilon(c_end, c_start);
+                }
+                (start, end)
+            }
+            other => panic!("Unexpected token {:?} in syntax tree", other),
+        }
+    }
+
+    fn edge_fragment(&mut self, start: char, end: char) -> (usize, usize) {
+        let from = self.add_state();
+        let to = self.add_state();
+        self.states[from].edges.push(((start, end), to));
+        (from, to)
+    }
+
+    fn closure(&self, set: Vec<usize>) -> Vec<usize> {
+        let mut closed: HashSet<usize> = set.iter().cloned().collect();
+        let mut pendientes = set;
+        while let Some(state) = pendientes.pop() {
+            for &next in &self.states[state].epsilon {
+                if closed.insert(next) {
+                    pendientes.push(next);
+                }
+            }
+        }
+        let mut closed: Vec<usize> = closed.into_iter().collect();
+        closed.sort();
+        closed
+    }
+
+    /// Construcción de subconjuntos. Los estados se nombran desde 'A' igual que
+    /// en DirectAFD, y los marcadores quedan como atributos de cada estado.
+    pub fn to_dfa(&self) -> Dfa {
+        let ranges: Vec<(char, char)> = self
+            .states
+            .iter()
+            .flat_map(|s| s.edges.iter().map(|(range, _)| *range))
+            .collect();
+        let mut classes = split_classes(&ranges);
+        classes.sort();
+
+        let mut dfa = Dfa::new('A', self.token_list.clone());
+        let mut names: HashMap<Vec<usize>, char> = HashMap::new();
+        let mut pending: VecDeque<Vec<usize>> = VecDeque::new();
+        let mut state_letter = 'A';
+
+        let start_set = self.closure(vec![self.start]);
+        names.insert(start_set.clone(), state_letter);
+        pending.push_back(start_set.clone());
+
+        // Al inicio de línea se cruzan también las transiciones ^
+        let bol_targets: Vec<usize> = start_set
+            .iter()
+            .flat_map(|&s| self.states[s].bol.clone())
+            .collect();
+        if !bol_targets.is_empty() {
+            let mut bol_set = start_set;
+            bol_set.extend(bol_targets);
+            let bol_set = self.closure(bol_set);
+            let bol_name = match names.get(&bol_set) {
+                Some(&name) => name,
+                None => {
+                    state_letter = next_state_name(state_letter);
+                    names.insert(bol_set.clone(), state_letter);
+                    pending.push_back(bol_set);
+                    state_letter
+                }
+            };
+            dfa.bol_start = Some(bol_name);
+        }
+
+        while let Some(set) = pending.pop_front() {
+            let name = names[&set];
+
+            let mut tokens: HashSet<&String> = HashSet::new();
+            for &state in &set {
+                if let Some(token) = &self.states[state].token {
+                    tokens.insert(token);
+                }
+                if let Some(owner) = &self.states[state].trail {
+                    dfa.trail.entry(name).or_default().insert(owner.clone());
+                }
+            }
+            if let Some(winner) = self.token_list.iter().find(|t| tokens.contains(t)) {
+                dfa.accept.insert(name, winner.clone());
+            }
+
+            let mut row = HashMap::new();
+            for &(start, end) in &classes {
+                let mut next: Vec<usize> = Vec::new();
+                for &state in &set {
+                    for &((s, e), to) in &self.states[state].edges {
+                        if s <= start && end <= e {
+                            next.push(to);
+                        }
+                    }
+                }
+                if next.is_empty() {
+                    continue;
+                }
+                let next = self.closure(next);
+                let dest = match names.get(&next) {
+                    Some(&dest) => dest,
+                    None => {
+                        state_letter = next_state_name(state_letter);
+                        names.insert(next.clone(), state_letter);
+                        pending.push_back(next);
+                        state_letter
+                    }
+                };
+                row.insert(class_key(start, end), dest);
+            }
+            dfa.transitions.insert(name, row);
+        }
+        dfa
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dfa::isomorphic;
+    use crate::lexer_builder::{Construction, LexerBuilder};
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+
+    #[test]
+    fn test_thompson_matches_direct() {
+        let patterns = [
+            "while",
+            "[a-z]([a-z]|[0-9])*",
+            "-?[0-9]+(\\.[0-9]*)?",
+            "[0-9]+/\\.\\.",
+            "\\.\\.",
+            "^#[a-z]+",
+            "(ab|a)*b?",
+            " ",
+        ];
+        // (ab|a)*b? acepta ε, se permite para probar ese caso
+        let mut builder = LexerBuilder::new();
+        builder.allow_empty(true);
+        for (index, pattern) in patterns.iter().enumerate() {
+            builder.add_rule(index, parse_rule(pattern));
+        }
+        let direct = minimize_dfa(&builder.build());
+        builder.construction(Construction::Thompson);
+        let thompson = minimize_dfa(&builder.build());
+        assert!(isomorphic(&direct, &thompson));
+    }
+}
@@ -1,112 +1,90 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
-fn leer_cadena(
-    state_map: &HashMap<char, HashMap<String, char>>,
-    input: &str,
-    first_state: char,
-) -> HashSet<char> {
-    let mut current_states = HashSet::new();
-    let mut next_state = HashSet::new();
-    current_states.insert(first_state);
-    let mut chars = input.chars().peekable();
-    let mut remaining = input;
+use crate::dfa::Dfa;
+
+// Los estados que cierran la parte r de r/s traen el token como atributo;
+// al pasar por ellos se guarda cuántos caracteres se habían leído.
+fn marcar_contexto(dfa: &Dfa, state: char, marcas: &mut HashMap<String, usize>, leidos: usize) {
+    if let Some(tokens) = dfa.trail.get(&state) {
+        for token in tokens {
+            marcas.insert(token.clone(), leidos);
+        }
+    }
+}
+
+fn leer_cadena(dfa: &Dfa, input: &str, inicio_de_linea: bool) -> (Option<char>, HashMap<String, usize>) {
+    let mut marcas = HashMap::new();
+    // Al inicio de línea también valen las reglas ancladas con ^
+    let mut current_state = match dfa.bol_start {
+        Some(bol_state) if inicio_de_linea => bol_state,
+        _ => dfa.start,
+    };
+    marcar_contexto(dfa, current_state, &mut marcas, 0);
 
     // println!("--- INICIANDO SIMULACIÓN ---");
     // println!("Cadena de entrada: \"{}\"", input);
-    // println!("Estado inicial: '{:?}'\n", current_states);
+    // println!("Estado inicial: '{:?}'\n", current_state);
 
-    while let Some(symbol) = chars.peek().copied() {
+    for (leidos, symbol) in input.chars().enumerate() {
         // println!("Símbolo a procesar: '{}'", symbol);
-        // println!("Cadena restante: \"{}\"", remaining);
-
-        for &current_state in &current_states {
-            if let Some(transitions) = state_map.get(&current_state) {
-                // println!("Posibles transiciones desde '{}': {:?}", current_state, transitions);
-
-                for (key, &state) in transitions {
-                    // println!("  Probando clave de transición: \"{}\"", key);
-
-                    if key.len() == 1 && key.chars().next().unwrap() == symbol {
-                        // Literales
-                        // println!("  → Coincidencia exacta con literal '{}'", key);
-                        next_state.insert(state);
-                    } else if key.contains('-') {
-                        // Rangos
-                        let parts: Vec<char> = key.chars().collect();
-                        if parts.len() == 3 && parts[1] == '-' {
-                            let start = parts[0];
-                            let end = parts[2];
-                            if start <= symbol && symbol <= end {
-                                // println!("  → Coincidencia en rango '{}'", key);
-                                next_state.insert(state);
-                            }
-                        }
-                    }
-                }
+        match dfa.step(current_state, symbol) {
+            Some(next_state) => current_state = next_state,
+            None => {
+                // println!("No se encontraron más transiciones.");
+                return (None, marcas);
             }
         }
-
-        if next_state.is_empty() {
-            // println!("No se encontraron más transiciones.");
-            return HashSet::new();
-        }
-
-        chars.next();
-        remaining = &remaining[1..];
-        current_states = next_state.clone();
-        next_state.clear();
+        marcar_contexto(dfa, current_state, &mut marcas, leidos + 1);
     }
     // println!("\n--- SIMULACIÓN FINALIZADA ---\n");
-    // println!("Estados alcanzados: {:?}", current_states);
+    // println!("Estado alcanzado: {:?}", current_state);
 
-    current_states
+    (Some(current_state), marcas)
 }
 
-pub fn asignar_token(
-    state_map: &HashMap<char, HashMap<String, char>>,
-    input: &str,
-    first_state: char,
-    acceptance_states: &HashSet<char>,
-    token_list: &Vec<String>,
-) -> String {
-    // println!("input: {}",input);
+pub fn asignar_token(dfa: &Dfa, input: &str, inicio_de_linea: bool) -> String {
     // println!("=== DEBUG: asignar_token ===");
     // println!("Input recibido: {}", input);
-    // println!("Estado inicial: {}", first_state);
-    // println!("Estados de aceptación: {:?}", acceptance_states);
-    // println!("Token list: {:?}", token_list);
-
-    let last_state_list = leer_cadena(state_map, input, first_state);
-    // println!("Últimos estados alcanzados: {:?}", last_state_list);
+    // println!("Estado inicial: {}", dfa.start);
+    // println!("Estados de aceptación: {:?}", dfa.accept);
 
-    let mut valid_transitions = HashSet::new();
+    let (last_state, _marcas) = leer_cadena(dfa, input, inicio_de_linea);
+    // println!("Último estado alcanzado: {:?}", last_state);
 
-    for &state in &last_state_list {
-        if let Some(transitions) = state_map.get(&state) {
-            // println!("Estado {} tiene transiciones: {:?}", state, transitions);
-            for (transition, &next_state) in transitions {
-                if acceptance_states.contains(&next_state) {
-                    // println!(
-                    //     "Transición válida encontrada: '{}' -> Estado {}",
-                    //     transition, next_state
-                    // );
-                    valid_transitions.insert(transition.clone()); // Guardamos la transición
-                }
-            }
-        } else {
-            // println!("Estado {} no tiene transiciones.", state);
-        }
+    match last_state.and_then(|state| dfa.accept.get(&state)) {
+        Some(token) => token.clone(),
+        // Ningún token encontrado, devolviendo 'UNKNOWN'
+        None => String::from("UNKNOWN"),
     }
-    // println!("VALID {:?}",valid_transitions);
-    // println!("Transiciones válidas detectadas: {:?}", valid_transitions);
+}
 
-    for token in token_list {
-        if valid_transitions.contains(token) {
-            // println!("Token encontrado en la lista: {}", token);
-            return token.clone();
-        }
+/// Para un token con contexto posterior (r/s), devuelve cuántos caracteres de
+/// `input` pertenecen a r. `None` si el token no tiene contexto posterior.
+/// Si r puede terminar en varias posiciones vale la última marca, es decir, la
+/// r más larga que deja a s el resto.
+pub fn fin_de_lexema(dfa: &Dfa, input: &str, token: &str, inicio_de_linea: bool) -> Option<usize> {
+    let (_state, marcas) = leer_cadena(dfa, input, inicio_de_linea);
+    marcas.get(token).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer_builder::LexerBuilder;
+    use crate::minimize::minimize_dfa;
+    use crate::regex_parser::parse_rule;
+
+    #[test]
+    fn test_digits_do_not_follow_token_markers() {
+        // Los ids de las reglas (3 y 7) son también caracteres de la entrada
+        let mut builder = LexerBuilder::new();
+        builder.add_rule(3, parse_rule("[a-z]+"));
+        builder.add_rule(7, parse_rule("[0-9]"));
+        let dfa = minimize_dfa(&builder.build());
+        assert_eq!(asignar_token(&dfa, "ab", false), "3");
+        assert_eq!(asignar_token(&dfa, "7", false), "7");
+        assert_eq!(asignar_token(&dfa, "a7", false), "UNKNOWN");
+        assert_eq!(asignar_token(&dfa, "a3", false), "UNKNOWN");
+        assert_eq!(asignar_token(&dfa, "73", false), "UNKNOWN");
     }
-    
-    // Ningún token encontrado, devolviendo 'UNKNOWN'
-    String::from("UNKNOWN")
 }
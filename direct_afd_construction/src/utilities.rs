@@ -1,23 +1,21 @@
-use crate::direct_afd::DirectAFD;
-use crate::inf_to_pos::Token;
-use crate::inf_to_pos;
-use crate::grammar_tree;
+use crate::dfa::Dfa;
+use crate::grammar_tree::TreeNode;
+use crate::lexer_builder::LexerBuilder;
 use crate::minimize::minimize_dfa;
-use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
-pub fn generate(regx: String)->(HashMap<char, HashMap<String, char>>,HashSet<char>,char,Vec<String>){
-    let postfix: Vec<Token> = inf_to_pos::inf_to_pos(&regx);
-    let mut gtree = grammar_tree::Tree::new();
-    let root = gtree.generate(postfix);
-    // println!(
-    //             "Árbol Sintáctico:\n{}",
-    //             (*root).clone().print_tree(0, "root\n")
-    //         );
-    let gtree_ref = Rc::new(gtree);
-    let mut afd = DirectAFD::new(gtree_ref);
-    afd.generate_afd();
-    let (state_map, acceptance_states, token_list) = afd.create_states();
-    let (minimized_map, minimized_accept_states, minimized_start) =
-    minimize_dfa(&state_map, &acceptance_states);
-    (minimized_map, minimized_accept_states,minimized_start, token_list)
-}
\ No newline at end of file
+pub fn generate(rules: Vec<(usize, TreeNode)>)->Dfa{
+    let mut builder = LexerBuilder::new();
+    for (index, rule) in rules {
+        builder.add_rule(index, rule);
+    }
+    minimize_dfa(&builder.build())
+}
+
+// Los estados se nombran con chars consecutivos desde 'A'; se saltan los
+// surrogates para que specs con miles de estados no se queden sin nombres.
+pub fn next_state_name(state: char) -> char {
+    let mut next = state as u32 + 1;
+    if (0xD800..=0xDFFF).contains(&next) {
+        next = 0xE000;
+    }
+    char::from_u32(next).expect("Ran out of state names")
+}
@@ -1,6 +1,8 @@
 use petgraph::dot::Dot;
 use petgraph::Graph;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+
+use crate::dfa::Dfa;
 use std::fs::File;
 use std::io::Write;
 use std::process::Command;
@@ -65,19 +67,22 @@ pub fn generate_graph(ginfo: &HashMap<char, HashMap<String, char>>, states: &Vec
     graph
 }
 
-pub fn render(ginfo: &HashMap<char, HashMap<String, char>>, accept: &HashSet<char>, dest: &str) {
-    let all_states = get_all_states(&ginfo);
-    let graph =generate_graph(ginfo, &all_states);
+pub fn render(dfa: &Dfa, dest: &str) {
+    let mut all_states = get_all_states(&dfa.transitions);
+    if !all_states.contains(&dfa.start.to_string()) {
+        all_states.push(dfa.start.to_string());
+    }
+    let graph =generate_graph(&dfa.transitions, &all_states);
     let start_n  = graph
     .node_indices()
-    .find(|&i| graph[i] == "A")
+    .find(|&i| graph[i] == dfa.start.to_string())
     .expect("Node not found");
     let start = format!("\"\" [style=invisible, width=0, height=0];\n\"\" -> {:?};\n", start_n.index());
 
     let mut dot_output = format!("{}", Dot::with_config(&graph, &[]));
     dot_output.insert_str(dot_output.len() - 2, &start);
 
-    for node in accept {
+    for node in dfa.accept.keys() {
         let tem_node = graph
         .node_indices()
         .find(|&i| graph[i] == node.to_string())